@@ -1,67 +1,189 @@
+//! Allocator generations identify the sequence of kills an allocator has made.
+//!
+//! Each generation is an epoch, the number of Ids killed so far, and a checksum, the wrapping sum of
+//! a mixed hash of every killed Id. A storage that missed a batch always has a different epoch.
+//! The checksum does not depend on the order of kills and never loses early ones,
+//! so two histories with the same epoch only compare equal if they killed the same Ids
+//! or their 64-bit checksums collide.
+
 use crate::id::UntypedId;
+use crate::layout::IdLayout;
 use crate::Id;
 use force_derive::*;
 use ref_cast::RefCast;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::marker::PhantomData;
 
+/// The finalizer from SplitMix64, so that Ids that differ by a single bit have unrelated hashes
 #[inline]
-fn wrapping_shl_bit_xor(hash: &mut u64, id: UntypedId) {
-    let bits = id.bits();
-    *hash <<= 1;
-    *hash ^= bits;
+fn mix(id: UntypedId) -> u64 {
+    let mut z = id.bits().wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct UntypedArenaGen(u64);
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UntypedArenaGen {
+    pub(crate) epoch: u64,
+    pub(crate) checksum: u64,
+}
 
 impl UntypedArenaGen {
     #[inline]
     pub(crate) fn increment_gen(&mut self, id: UntypedId) {
-        wrapping_shl_bit_xor(&mut self.0, id);
+        self.epoch += 1;
+        self.checksum = self.checksum.wrapping_add(mix(id));
     }
 
     #[inline]
-    pub(crate) fn update(&mut self, before: &UntypedAllocGen, after: &UntypedAllocGen) {
-        assert_eq!(self, before);
-        self.0 = after.0;
+    pub(crate) fn try_update(
+        &mut self,
+        before: &UntypedAllocGen,
+        after: &UntypedAllocGen,
+    ) -> Result<(), OutOfSync> {
+        self.compare(before)?;
+        self.epoch = after.epoch;
+        self.checksum = after.checksum;
+        Ok(())
+    }
+
+    /// Describes how this generation differs from the allocator generation
+    pub(crate) fn compare(&self, gen: &UntypedAllocGen) -> Result<(), OutOfSync> {
+        if self.epoch < gen.epoch {
+            Err(OutOfSync::Behind {
+                kills: gen.epoch - self.epoch,
+                batches: None,
+            })
+        } else if self.epoch > gen.epoch {
+            Err(OutOfSync::Ahead {
+                kills: self.epoch - gen.epoch,
+            })
+        } else if self.checksum != gen.checksum {
+            Err(OutOfSync::Diverged)
+        } else {
+            Ok(())
+        }
     }
 }
 
+/// The reason an `ArenaGen` could not be updated by a batch of kills
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutOfSync {
+    /// The storage missed earlier kills, and must catch up or be rebuilt
+    Behind {
+        kills: u64,
+        /// The number of missed batches, if the allocator kept a log of them
+        batches: Option<usize>,
+    },
+    /// The storage has seen kills that came after this batch, or from another allocator
+    Ahead { kills: u64 },
+    /// The storage has seen as many kills as the allocator, but not the same ones
+    Diverged,
+}
+
+impl Display for OutOfSync {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutOfSync::Behind {
+                kills,
+                batches: Some(batches),
+            } => write!(
+                f,
+                "storage is behind the allocator by {} kills in {} batches",
+                kills, batches
+            ),
+            OutOfSync::Behind {
+                kills,
+                batches: None,
+            } => write!(f, "storage is behind the allocator by {} kills", kills),
+            OutOfSync::Ahead { kills } => {
+                write!(f, "storage is ahead of the allocator by {} kills", kills)
+            }
+            OutOfSync::Diverged => write!(f, "storage has diverged from the allocator"),
+        }
+    }
+}
+
+impl Error for OutOfSync {}
+
+/// The reason the Ids killed since an `ArenaGen` could not be returned
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KilledSinceError {
+    /// The kill log no longer reaches back to the generation, so the storage must be rebuilt
+    Truncated { kills: u64 },
+    /// The generation is ahead of the allocator or has diverged from it
+    OutOfSync(OutOfSync),
+}
+
+impl Display for KilledSinceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KilledSinceError::Truncated { kills } => write!(
+                f,
+                "kill log has been truncated past the {} kills the storage is missing",
+                kills
+            ),
+            KilledSinceError::OutOfSync(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for KilledSinceError {}
+
 impl PartialEq<UntypedAllocGen> for UntypedArenaGen {
     #[inline]
     fn eq(&self, other: &UntypedAllocGen) -> bool {
-        self.0.eq(&other.0)
+        self.epoch == other.epoch && self.checksum == other.checksum
     }
 }
 
-#[derive(Debug, Default)]
-pub struct UntypedAllocGen(u64);
+#[derive(Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UntypedAllocGen {
+    pub(crate) epoch: u64,
+    pub(crate) checksum: u64,
+}
 
 impl UntypedAllocGen {
     #[inline]
     pub(crate) fn clone(&self) -> Self {
-        Self(self.0)
+        Self {
+            epoch: self.epoch,
+            checksum: self.checksum,
+        }
     }
 
     #[inline]
     pub(crate) fn increment_gen(&mut self, id: UntypedId) {
-        wrapping_shl_bit_xor(&mut self.0, id);
+        self.epoch += 1;
+        self.checksum = self.checksum.wrapping_add(mix(id));
     }
 }
 
 #[repr(transparent)]
 #[derive(Debug, ForceClone, ForceDefault, RefCast)]
-pub struct ArenaGen<Arena>(UntypedArenaGen, PhantomData<Arena>);
+pub struct ArenaGen<Arena>(UntypedArenaGen, PhantomData<fn() -> Arena>);
 
 impl<Arena> ArenaGen<Arena> {
     #[inline]
-    pub fn increment_gen(&mut self, id: Id<Arena>) {
-        self.0.increment_gen(id.untyped);
+    pub(crate) fn untyped(&self) -> &UntypedArenaGen {
+        &self.0
+    }
+
+    #[inline]
+    pub fn increment_gen<L: IdLayout>(&mut self, id: Id<Arena, L>) {
+        self.0.increment_gen(id.untyped());
     }
 
     #[inline]
-    pub(crate) fn update(&mut self, before: &AllocGen<Arena>, after: &AllocGen<Arena>) {
-        self.0.update(&before.0, &after.0);
+    pub(crate) fn try_update(
+        &mut self,
+        before: &AllocGen<Arena>,
+        after: &AllocGen<Arena>,
+    ) -> Result<(), OutOfSync> {
+        self.0.try_update(&before.0, &after.0)
     }
 }
 
@@ -75,12 +197,17 @@ impl<Arena> PartialEq<AllocGen<Arena>> for ArenaGen<Arena> {
 /// Does not impl Copy/Clone so it must be borrowed from the Allocator
 #[repr(transparent)]
 #[derive(Debug, RefCast)]
-pub struct AllocGen<Arena>(UntypedAllocGen, PhantomData<Arena>);
+pub struct AllocGen<Arena>(UntypedAllocGen, PhantomData<fn() -> Arena>);
 
 impl<Arena> AllocGen<Arena> {
     pub(crate) fn new(gen: UntypedAllocGen) -> Self {
         Self(gen, PhantomData)
     }
+
+    #[inline]
+    pub(crate) fn untyped(&self) -> &UntypedAllocGen {
+        &self.0
+    }
 }
 
 #[cfg(test)]
@@ -103,4 +230,152 @@ mod tests {
 
         assert!(arena_gen.eq(&alloc_gen));
     }
+
+    #[test]
+    fn try_update_describes_how_storage_is_out_of_sync() {
+        let ids = (0..3).map(UntypedId::first).collect::<Vec<_>>();
+        let mut allocator = UntypedAllocGen::default();
+        let mut storage = UntypedArenaGen::default();
+
+        let before = allocator.clone();
+        allocator.increment_gen(ids[0]);
+        storage.try_update(&before, &allocator).unwrap();
+
+        // storage misses a batch of two
+        let before = allocator.clone();
+        allocator.increment_gen(ids[1]);
+        allocator.increment_gen(ids[2]);
+        let missed = allocator.clone();
+        let before_next = allocator.clone();
+        allocator.increment_gen(UntypedId::first(3));
+
+        let behind = OutOfSync::Behind {
+            kills: 2,
+            batches: None,
+        };
+        assert_eq!(Err(behind), storage.try_update(&before_next, &allocator));
+        assert_eq!(
+            Err(OutOfSync::Ahead { kills: 1 }),
+            arena(&allocator).try_update(&before_next, &allocator)
+        );
+
+        let mut diverged = storage.clone();
+        diverged.increment_gen(UntypedId::first(4));
+        diverged.increment_gen(UntypedId::first(5));
+        assert_eq!(
+            Err(OutOfSync::Diverged),
+            diverged.try_update(&before_next, &allocator)
+        );
+
+        // a failed update leaves the storage untouched
+        storage.try_update(&before, &missed).unwrap();
+        storage.try_update(&before_next, &allocator).unwrap();
+        assert!(storage == allocator);
+    }
+
+    fn arena(gen: &UntypedAllocGen) -> UntypedArenaGen {
+        UntypedArenaGen {
+            epoch: gen.epoch,
+            checksum: gen.checksum,
+        }
+    }
+
+    mod properties {
+        use super::*;
+        use crate::gen::Gen;
+        use proptest::collection::{btree_set, vec};
+        use proptest::prelude::*;
+        use proptest::sample::Index;
+
+        fn id(index: u32, gen: u32) -> UntypedId {
+            UntypedId {
+                index,
+                gen: Gen::new(gen).unwrap(),
+            }
+        }
+
+        fn ids(max: usize) -> impl Strategy<Value = Vec<UntypedId>> {
+            btree_set((any::<u32>(), 1..=u32::MAX), 1..max)
+                .prop_map(|ids| ids.into_iter().map(|(i, g)| id(i, g)).collect())
+                .prop_shuffle()
+        }
+
+        fn alloc_gen<'a>(ids: impl IntoIterator<Item = &'a UntypedId>) -> UntypedAllocGen {
+            let mut gen = UntypedAllocGen::default();
+            for id in ids {
+                gen.increment_gen(*id);
+            }
+            gen
+        }
+
+        fn arena_gen<'a>(ids: impl IntoIterator<Item = &'a UntypedId>) -> UntypedArenaGen {
+            let mut gen = UntypedArenaGen::default();
+            for id in ids {
+                gen.increment_gen(*id);
+            }
+            gen
+        }
+
+        proptest! {
+            #[test]
+            fn missing_any_batch_is_detected(
+                ids in ids(200),
+                cuts in vec(any::<Index>(), 2),
+                missed in any::<Index>(),
+            ) {
+                let mut cuts = cuts
+                    .iter()
+                    .map(|cut| cut.index(ids.len() + 1))
+                    .collect::<Vec<_>>();
+                cuts.sort_unstable();
+                let batches = [&ids[..cuts[0]], &ids[cuts[0]..cuts[1]], &ids[cuts[1]..]];
+                let missed = missed.index(batches.len());
+                prop_assume!(!batches[missed].is_empty());
+
+                let seen = batches
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != missed)
+                    .flat_map(|(_, batch)| batch.iter());
+
+                prop_assert!(arena_gen(seen) != alloc_gen(&ids));
+            }
+
+            #[test]
+            fn order_of_kills_does_not_matter(ids in ids(200)) {
+                let mut reversed = ids.clone();
+                reversed.reverse();
+
+                prop_assert!(arena_gen(&reversed) == alloc_gen(&ids));
+            }
+
+            #[test]
+            fn different_kills_of_the_same_length_are_detected(
+                ids in ids(200),
+                other in ids(200),
+            ) {
+                let other = &other[..other.len().min(ids.len())];
+                let ids = &ids[..other.len()];
+                let mut sorted = (ids.to_vec(), other.to_vec());
+                sorted.0.sort();
+                sorted.1.sort();
+                prop_assume!(sorted.0 != sorted.1);
+
+                prop_assert!(arena_gen(other) != alloc_gen(ids));
+            }
+
+            #[test]
+            fn early_kills_are_not_forgotten(
+                ids in ids(200),
+                first in (any::<u32>(), 1..=u32::MAX),
+            ) {
+                let first = id(first.0, first.1);
+                prop_assume!(first != ids[0]);
+                let tail = &ids[1..];
+
+                let seen = std::iter::once(&first).chain(tail);
+                prop_assert!(arena_gen(seen) != alloc_gen(&ids));
+            }
+        }
+    }
 }
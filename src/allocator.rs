@@ -1,74 +1,545 @@
-use crate::alloc_gen::{AllocGen, UntypedAllocGen};
-use crate::gen::Gen;
+use crate::alloc_gen::{AllocGen, KilledSinceError, OutOfSync, UntypedAllocGen, UntypedArenaGen};
+use crate::checkpoint::{Checkpoint, UntypedCheckpoint};
+use crate::entries::{Entries, CHUNK};
+use crate::fork::{Fork, ForkConflict};
+use crate::frozen::FrozenAllocator;
+use crate::gen::{Gen, GenExhaustion};
 use crate::id::UntypedId;
 use crate::id::*;
+use crate::layout::{IdLayout, Split32x32};
 use crate::range::{IdRange, UntypedIdRange};
+use crate::remap::{IdRemap, UntypedIdRemap};
 use crate::valid::Valid;
-use crate::{ArenaGen, Fixed, Validator};
+use crate::{ArenaGen, Dynamic, Fixed, Validator};
 use force_derive::*;
 use nonmax::NonMaxU32;
 use ref_cast::RefCast;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
 
-#[derive(Debug, Default)]
-pub struct UntypedAllocator {
-    entries: Vec<Entry>,
-    next_dead: Option<NonMaxU32>,
-    gen: UntypedAllocGen,
+#[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        try_from = "serde_impl::UntypedAllocatorData<L>",
+        bound = "L: IdLayout"
+    )
+)]
+pub struct UntypedAllocator<L = Split32x32> {
+    pub(crate) entries: Entries,
+    pub(crate) free: FreeList,
+    pub(crate) gen: UntypedAllocGen,
+    pub(crate) exhaustion: GenExhaustion,
+    pub(crate) quarantine: Quarantine,
+    /// The generation given to new slots, raised when dead slots are truncated
+    /// so that their old Ids cannot be handed out again
+    pub(crate) fresh_gen: Gen,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) retired: usize,
+    pub(crate) reserved: Reserved,
+    pub(crate) kill_log: KillLog,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) layout: PhantomData<L>,
 }
 
-#[derive(Debug)]
-enum Entry {
+impl<L> Default for UntypedAllocator<L> {
+    fn default() -> Self {
+        Self {
+            entries: Entries::default(),
+            free: FreeList::default(),
+            gen: UntypedAllocGen::default(),
+            exhaustion: GenExhaustion::default(),
+            quarantine: Quarantine::default(),
+            fresh_gen: Gen::default(),
+            retired: 0,
+            reserved: Reserved::default(),
+            kill_log: KillLog::default(),
+            layout: PhantomData,
+        }
+    }
+}
+
+impl<L> Clone for UntypedAllocator<L> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            free: self.free.clone(),
+            gen: self.gen.clone(),
+            exhaustion: self.exhaustion,
+            quarantine: self.quarantine.clone(),
+            fresh_gen: self.fresh_gen,
+            retired: self.retired,
+            reserved: self.reserved.clone(),
+            kill_log: self.kill_log.clone(),
+            layout: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Entry {
     Dead(Dead),
     Alive(UntypedId),
+    /// The generation of this slot is exhausted and it will not be reused
+    Retired,
 }
 
-#[derive(Debug)]
-struct Dead {
-    next_dead: Option<NonMaxU32>,
-    gen: Gen,
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Dead {
+    pub(crate) next_dead: Option<NonMaxU32>,
+    pub(crate) gen: Gen,
 }
 
 impl Entry {
-    fn dead(&self) -> Option<&Dead> {
+    pub(crate) fn dead(&self) -> Option<&Dead> {
         match self {
             Entry::Dead(dead) => Some(dead),
-            Entry::Alive(_) => None,
+            _ => None,
         }
     }
 
-    fn alive(&self) -> Option<UntypedId> {
+    pub(crate) fn alive(&self) -> Option<UntypedId> {
         match self {
-            Entry::Dead(_) => None,
             Entry::Alive(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+/// Determines which dead slot is handed out next when an allocator reuses an index
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SlotReuse {
+    /// The most recently killed slot is reused first
+    #[default]
+    Lifo,
+    /// The least recently killed slot is reused first, which keeps stale Ids dead for as long as possible
+    Fifo,
+    /// The lowest free index is reused first, which keeps living Ids packed at the front
+    LowestIndex,
+}
+
+/// The dead slots that are available for reuse.
+/// `Lifo` and `Fifo` link through `Dead::next_dead`, `LowestIndex` uses a min-heap.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct FreeList {
+    pub(crate) reuse: SlotReuse,
+    pub(crate) head: Option<NonMaxU32>,
+    pub(crate) tail: Option<NonMaxU32>,
+    pub(crate) lowest: BinaryHeap<Reverse<u32>>,
+}
+
+impl FreeList {
+    pub(crate) fn new(reuse: SlotReuse) -> Self {
+        Self {
+            reuse,
+            ..Default::default()
+        }
+    }
+
+    /// Marks the entry as dead and makes it available for reuse
+    #[inline]
+    pub(crate) fn push(&mut self, entries: &mut Entries, index: u32, gen: Gen) {
+        let next_dead = match self.reuse {
+            SlotReuse::Lifo => self.head,
+            SlotReuse::Fifo | SlotReuse::LowestIndex => None,
+        };
+        entries[index as usize] = Entry::Dead(Dead { next_dead, gen });
+
+        match self.reuse {
+            SlotReuse::Lifo => {
+                self.head = NonMaxU32::new(index);
+            }
+            SlotReuse::Fifo => {
+                let index = NonMaxU32::new(index);
+                match self.tail.map(|tail| &mut entries[tail.get() as usize]) {
+                    Some(Entry::Dead(tail)) => tail.next_dead = index,
+                    _ => self.head = index,
+                }
+                self.tail = index;
+            }
+            SlotReuse::LowestIndex => {
+                self.lowest.push(Reverse(index));
+            }
+        }
+    }
+
+    /// The next index to be reused
+    #[inline]
+    pub(crate) fn peek(&self) -> Option<u32> {
+        match self.reuse {
+            SlotReuse::Lifo | SlotReuse::Fifo => self.head.map(|head| head.get()),
+            SlotReuse::LowestIndex => self.lowest.peek().map(|&Reverse(index)| index),
+        }
+    }
+
+    /// Removes the next index to be reused, leaving its entry untouched
+    #[inline]
+    pub(crate) fn pop(&mut self, entries: &Entries) -> Option<u32> {
+        match self.reuse {
+            SlotReuse::Lifo | SlotReuse::Fifo => {
+                let index = self.head?.get();
+                self.head = entries.get(index as usize)?.dead()?.next_dead;
+                if self.head.is_none() {
+                    self.tail = None;
+                }
+                Some(index)
+            }
+            SlotReuse::LowestIndex => self.lowest.pop().map(|Reverse(index)| index),
+        }
+    }
+}
+
+/// Killed slots that are held back from the free list until enough ticks have passed
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Quarantine {
+    pub(crate) delay: u64,
+    pub(crate) tick: u64,
+    pub(crate) slots: VecDeque<(u64, u32)>,
+}
+
+/// The most recent batches of kills, each with the allocator generation from before it.
+/// Batches are contiguous, so the log can be replayed from any generation it still reaches back to.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct KillLog {
+    capacity: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    batches: VecDeque<(UntypedAllocGen, Vec<UntypedId>)>,
+}
+
+impl Clone for KillLog {
+    fn clone(&self) -> Self {
+        Self {
+            capacity: self.capacity,
+            batches: self
+                .batches
+                .iter()
+                .map(|(before, ids)| (before.clone(), ids.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl KillLog {
+    #[inline]
+    pub(crate) fn push(&mut self, before: UntypedAllocGen, ids: &[UntypedId]) {
+        if self.capacity == 0 || ids.is_empty() {
+            return;
+        }
+        if self.batches.len() == self.capacity {
+            self.batches.pop_front();
+        }
+        self.batches.push_back((before, ids.to_vec()));
+    }
+
+    /// Called when the allocator generation changes without a batch being logged
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.batches.clear();
+    }
+
+    /// Drops the batches that come after the generation, or the whole log if it does not lead up to it
+    fn rewind(&mut self, gen: &UntypedAllocGen) {
+        while matches!(self.batches.back(), Some((before, _)) if before.epoch >= gen.epoch) {
+            self.batches.pop_back();
+        }
+
+        if !self.leads_to(gen) {
+            self.batches.clear();
+        }
+    }
+
+    /// True if each batch starts where the last one ended and the last one ends at the generation
+    fn leads_to(&self, gen: &UntypedAllocGen) -> bool {
+        let mut end = match self.batches.front() {
+            Some((before, _)) => before.clone(),
+            None => return true,
+        };
+
+        for (before, ids) in &self.batches {
+            if *before != end || ids.is_empty() {
+                return false;
+            }
+            for id in ids {
+                end.increment_gen(*id);
+            }
+        }
+        end == *gen
+    }
+
+    /// The number of batches that end after `from` and start before `until`, if the log reaches back to `from`
+    fn batches_between(&self, from: u64, until: u64) -> Option<usize> {
+        let (oldest, _) = self.batches.front()?;
+        if oldest.epoch > from {
+            return None;
+        }
+
+        let between = |(before, ids): &&(UntypedAllocGen, Vec<UntypedId>)| {
+            before.epoch + ids.len() as u64 > from && before.epoch < until
+        };
+        Some(self.batches.iter().filter(between).count())
+    }
+
+    /// The Ids killed since the generation, oldest first
+    fn since(&self, gen: &UntypedArenaGen) -> Option<Result<Vec<UntypedId>, OutOfSync>> {
+        let position = self.batches.iter().position(|(before, ids)| {
+            before.epoch <= gen.epoch && gen.epoch < before.epoch + ids.len() as u64
+        })?;
+
+        // the generation may fall within a batch if the storage incremented it one Id at a time
+        let (before, ids) = &self.batches[position];
+        let mut replay = before.clone();
+        let mut skip = 0;
+        while replay.epoch < gen.epoch {
+            replay.increment_gen(ids[skip]);
+            skip += 1;
+        }
+        if *gen != replay {
+            return Some(Err(OutOfSync::Diverged));
+        }
+
+        let later = self.batches.iter().skip(position + 1);
+        let killed = ids[skip..]
+            .iter()
+            .chain(later.flat_map(|(_, ids)| ids))
+            .copied()
+            .collect();
+        Some(Ok(killed))
+    }
+}
+
+/// Ids reserved through a shared reference, which become entries at the next flush
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Reserved {
+    /// Zero if no dead slot has been reserved, `RESERVED_ALL` once the free list is used up,
+    /// otherwise one more than the index of the next dead slot to reserve
+    next_dead: AtomicU64,
+    past_end: AtomicUsize,
+}
+
+impl Clone for Reserved {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            next_dead: AtomicU64::new(self.next_dead.load(Ordering::Acquire)),
+            past_end: AtomicUsize::new(self.past_end.load(Ordering::Acquire)),
         }
     }
 }
 
-impl UntypedAllocator {
+const RESERVED_ALL: u64 = u64::MAX;
+
+impl<L: IdLayout> UntypedAllocator<L> {
+    #[inline]
+    pub fn with_gen_exhaustion(mut self, exhaustion: GenExhaustion) -> Self {
+        self.exhaustion = exhaustion;
+        self
+    }
+
+    /// Dead slots are carried over to the new policy
+    pub fn with_slot_reuse(mut self, reuse: SlotReuse) -> Self {
+        self.flush();
+        let mut free = FreeList::new(reuse);
+        while let Some(index) = self.free.pop(&self.entries) {
+            if let Some(&Dead { gen, .. }) = self.entries[index as usize].dead() {
+                free.push(&mut self.entries, index, gen);
+            }
+        }
+        self.free = free;
+        self
+    }
+
+    /// Killed slots are not reused until `advance_tick` has been called `ticks` times
+    #[inline]
+    pub fn with_quarantine(mut self, ticks: u64) -> Self {
+        self.quarantine.delay = ticks;
+        self
+    }
+
+    /// Keeps the most recent `batches` batches of kills for `killed_since`.
+    ///
+    /// Each call to `kill` is logged as a batch of its own, so a log that has to reach back
+    /// over many single kills needs a capacity to match, or the kills should be batched with `kill_multiple`.
+    #[inline]
+    pub fn with_kill_log(mut self, batches: usize) -> Self {
+        self.kill_log.capacity = batches;
+        while self.kill_log.batches.len() > batches {
+            self.kill_log.batches.pop_front();
+        }
+        self
+    }
+
+    /// Releases any quarantined slots whose delay has passed
+    pub fn advance_tick(&mut self) {
+        self.flush();
+        let quarantine = &mut self.quarantine;
+        quarantine.tick += 1;
+
+        while let Some(&(killed, index)) = quarantine.slots.front() {
+            if killed + quarantine.delay > quarantine.tick {
+                break;
+            }
+            quarantine.slots.pop_front();
+
+            if let Some(&Dead { gen, .. }) = self.entries[index as usize].dead() {
+                self.free.push(&mut self.entries, index, gen);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn tick(&self) -> u64 {
+        self.quarantine.tick
+    }
+
+    /// The number of killed slots that are waiting to be released by `advance_tick`
+    #[inline]
+    pub fn quarantined(&self) -> usize {
+        self.quarantine.slots.len()
+    }
+
+    /// The number of slots that have been permanently retired due to generation exhaustion
+    #[inline]
+    pub fn retired(&self) -> usize {
+        self.retired
+    }
+
+    /// Panics if the index space of the layout is exhausted
     #[inline]
     pub fn create(&mut self) -> UntypedId {
-        self.reuse_index().unwrap_or_else(|| self.create_new())
+        self.try_create().expect("Id index space exhausted")
+    }
+
+    /// Returns `None` if the index space of the layout is exhausted
+    #[inline]
+    pub fn try_create(&mut self) -> Option<UntypedId> {
+        self.flush();
+        self.reuse_index().or_else(|| self.create_new())
     }
 
+    /// Panics if the index space of the layout is exhausted
     #[inline]
     pub fn create_range(&mut self, len: usize) -> UntypedIdRange {
+        self.try_create_range(len)
+            .expect("Id index space exhausted")
+    }
+
+    /// Returns `None` if the index space of the layout is exhausted.
+    /// Panics if dead slots have been truncated, as new slots no longer start at the first generation.
+    #[inline]
+    pub fn try_create_range(&mut self, len: usize) -> Option<UntypedIdRange> {
+        assert_eq!(
+            Gen::default(),
+            self.fresh_gen,
+            "create_range cannot be used after dead slots are truncated"
+        );
+        self.flush();
+
         let start = self.entries.len();
         let end = start + len;
+        if end > L::MAX_INDEX as usize + 1 {
+            return None;
+        }
+
         for _ in 0..len {
             // guarantees that Ids are contiguous at the end of `self.entries`
             let _ = self.create_new();
         }
-        UntypedIdRange::new(start, end)
+        Some(UntypedIdRange::new(start, end))
+    }
+
+    /// Panics if the index space of the layout is exhausted
+    #[inline]
+    pub fn reserve(&self) -> UntypedId {
+        self.try_reserve().expect("Id index space exhausted")
+    }
+
+    /// Hands out an Id that becomes alive at the next `flush` or mutating call.
+    /// Dead slots are reserved in the order they would be reused, except with `SlotReuse::LowestIndex`
+    /// where only new slots are reserved.
+    /// Returns `None` if the index space of the layout is exhausted.
+    pub fn try_reserve(&self) -> Option<UntypedId> {
+        if self.free.reuse != SlotReuse::LowestIndex {
+            // the free list cannot change while `self` is shared, so it can be walked with a cursor
+            let next_dead = &self.reserved.next_dead;
+            let mut current = next_dead.load(Ordering::Acquire);
+            loop {
+                let index = match current {
+                    0 => self.free.head.map(|head| head.get()),
+                    RESERVED_ALL => None,
+                    index => Some((index - 1) as u32),
+                };
+                let index = match index {
+                    Some(index) => index,
+                    None => break,
+                };
+
+                let &Dead {
+                    next_dead: next,
+                    gen,
+                } = self.entries[index as usize].dead()?;
+                let next = next.map_or(RESERVED_ALL, |next| next.get() as u64 + 1);
+
+                match next_dead.compare_exchange_weak(
+                    current,
+                    next,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => return Some(UntypedId { index, gen }),
+                    Err(actual) => current = actual,
+                }
+            }
+        }
+
+        let len = self.entries.len();
+        let past_end = self
+            .reserved
+            .past_end
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |past_end| {
+                (len + past_end <= L::MAX_INDEX as usize).then_some(past_end + 1)
+            })
+            .ok()?;
+
+        Some(UntypedId {
+            index: (len + past_end) as u32,
+            gen: self.fresh_gen,
+        })
+    }
+
+    /// Makes reserved Ids alive
+    pub fn flush(&mut self) {
+        let next_dead = std::mem::take(self.reserved.next_dead.get_mut());
+        let past_end = std::mem::take(self.reserved.past_end.get_mut());
+
+        if next_dead != 0 {
+            let stop = match next_dead {
+                RESERVED_ALL => None,
+                index => NonMaxU32::new((index - 1) as u32),
+            };
+            while self.free.head != stop && self.reuse_index().is_some() {}
+        }
+
+        for _ in 0..past_end {
+            let _ = self.create_new();
+        }
     }
 
     #[inline]
     fn reuse_index(&mut self) -> Option<UntypedId> {
-        let index = self.next_dead?.get();
+        let index = self.free.pop(&self.entries)?;
         let entry = self.entries.get_mut(index as usize)?;
-        let &Dead { next_dead, gen } = entry.dead()?;
-
-        self.next_dead = next_dead;
+        let &Dead { gen, .. } = entry.dead()?;
 
         let id = UntypedId { index, gen };
         *entry = Entry::Alive(id);
@@ -76,22 +547,68 @@ impl UntypedAllocator {
     }
 
     #[inline]
-    fn create_new(&mut self) -> UntypedId {
+    fn create_new(&mut self) -> Option<UntypedId> {
         let index = self.entries.len();
-        let id = UntypedId::first(index);
+        if index > L::MAX_INDEX as usize {
+            return None;
+        }
+
+        let id = UntypedId {
+            index: index as u32,
+            gen: self.fresh_gen,
+        };
         self.entries.push(Entry::Alive(id));
-        id
+        Some(id)
     }
 
     #[inline]
     pub fn kill(&mut self, id: UntypedId) -> bool {
+        let before = self.gen.clone();
+        let killed = self.kill_unlogged(id);
+        if killed {
+            self.kill_log.push(before, &[id]);
+        }
+        killed
+    }
+
+    /// Kills each Id and logs those that were alive as a single batch
+    pub(crate) fn kill_batch<I>(&mut self, ids: I) -> Vec<UntypedId>
+    where
+        I: IntoIterator<Item = UntypedId>,
+    {
+        let before = self.gen.clone();
+        let killed = ids
+            .into_iter()
+            .filter(|id| self.kill_unlogged(*id))
+            .collect::<Vec<_>>();
+        self.kill_log.push(before, &killed);
+        killed
+    }
+
+    fn kill_unlogged(&mut self, id: UntypedId) -> bool {
+        self.flush();
         if let Some(entry) = self.entries.get_mut(id.index()) {
             if matches!(entry, Entry::Alive(living) if *living == id) {
-                *entry = Entry::Dead(Dead {
-                    next_dead: self.next_dead,
-                    gen: id.gen.next(),
-                });
-                self.next_dead = NonMaxU32::new(id.index);
+                let gen = self.exhaustion.next_gen(id, L::MAX_GEN);
+
+                match gen {
+                    Some(gen) if self.quarantine.delay == 0 => {
+                        self.free.push(&mut self.entries, id.index, gen);
+                    }
+                    Some(gen) => {
+                        *entry = Entry::Dead(Dead {
+                            next_dead: None,
+                            gen,
+                        });
+                        let slot = (self.quarantine.tick, id.index);
+                        self.quarantine.slots.push_back(slot);
+                    }
+                    None => {
+                        *entry = Entry::Retired;
+                        self.retired += 1;
+                    }
+                }
+
                 self.gen.increment_gen(id);
 
                 return true;
@@ -111,69 +628,362 @@ impl UntypedAllocator {
     pub fn ids(&self) -> impl Iterator<Item = UntypedId> + '_ {
         self.entries.iter().filter_map(Entry::alive)
     }
+
+    /// The number of slots, which is one more than the highest index in use
+    #[inline]
+    pub fn slots(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Moves living entries down into dead slots and truncates the dead slots left at the end.
+    /// Each moved Id is killed, and the returned remap gives the Id that replaces it.
+    /// Quarantined and retired slots are not moved or reused.
+    ///
+    /// The slots moved out of are freed without quarantine, as `fresh_gen` keeps truncated Ids from being reissued.
+    pub fn compact(&mut self) -> UntypedIdRemap {
+        self.flush();
+        let before = self.gen.clone();
+        let mut remap = UntypedIdRemap::default();
+        let quarantined = self.quarantined_slots();
+        let is_hole = |entry: &Entry, index: usize| entry.dead().is_some() && !quarantined[index];
+
+        let mut hole = 0;
+        let mut end = self.entries.len();
+        loop {
+            while hole < end && !is_hole(&self.entries[hole], hole) {
+                hole += 1;
+            }
+            while end > hole && self.entries[end - 1].alive().is_none() {
+                end -= 1;
+            }
+            if hole == end {
+                break;
+            }
+
+            // the free list is left stale until it is rebuilt below
+            if let (Some(old), Some(&Dead { gen, .. })) =
+                (self.entries[end - 1].alive(), self.entries[hole].dead())
+            {
+                let new = UntypedId {
+                    index: hole as u32,
+                    gen,
+                };
+                self.entries[hole] = Entry::Alive(new);
+                self.entries[end - 1] = match self.exhaustion.next_gen(old, L::MAX_GEN) {
+                    Some(gen) => Entry::Dead(Dead {
+                        next_dead: None,
+                        gen,
+                    }),
+                    None => {
+                        self.retired += 1;
+                        Entry::Retired
+                    }
+                };
+                self.gen.increment_gen(old);
+                remap.insert(old, new);
+            }
+        }
+
+        let moved = remap.iter().map(|(old, _)| old).collect::<Vec<_>>();
+        self.kill_log.push(before, &moved);
+        self.truncate_dead();
+        remap
+    }
+
+    /// Creates a new Id for each living Id in `other`, in order of index
+    pub fn import_from(&mut self, other: &UntypedAllocator<L>) -> UntypedIdRemap {
+        let mut remap = UntypedIdRemap::default();
+        for old in other.ids() {
+            remap.insert(old, self.create());
+        }
+        remap
+    }
+
+    /// Truncates the dead slots at the end without moving any living entries
+    pub fn trim(&mut self) {
+        self.flush();
+        if self.entries.last().and_then(Entry::dead).is_some() {
+            self.truncate_dead();
+        }
+    }
+
+    fn truncate_dead(&mut self) {
+        let quarantined = self.quarantined_slots();
+        let len = (0..self.entries.len())
+            .rposition(|index| self.entries[index].dead().is_none() || quarantined[index])
+            .map_or(0, |index| index + 1);
+
+        for index in len..self.entries.len() {
+            if let Entry::Dead(dead) = &self.entries[index] {
+                self.fresh_gen = self.fresh_gen.max(dead.gen);
+            }
+        }
+        self.entries.truncate(len);
+        self.rebuild_free_list(&quarantined);
+    }
+
+    /// Pushes every dead slot that is not quarantined onto an empty free list
+    fn rebuild_free_list(&mut self, quarantined: &[bool]) {
+        let mut free = (0..self.entries.len())
+            .filter(|&index| self.entries[index].dead().is_some() && !quarantined[index])
+            .collect::<Vec<_>>();
+
+        // reuse the lowest indices first
+        if self.free.reuse == SlotReuse::Lifo {
+            free.reverse();
+        }
+
+        self.free = FreeList::new(self.free.reuse);
+        for index in free {
+            if let Some(&Dead { gen, .. }) = self.entries[index].dead() {
+                self.free.push(&mut self.entries, index as u32, gen);
+            }
+        }
+    }
+
+    /// Entries are shared with the checkpoint, and a chunk is only copied when it is next written to
+    pub fn checkpoint(&mut self) -> UntypedCheckpoint<L> {
+        self.flush();
+
+        UntypedCheckpoint {
+            allocator: UntypedAllocator {
+                entries: self.entries.clone(),
+                free: self.free.clone(),
+                gen: self.gen.clone(),
+                exhaustion: self.exhaustion,
+                quarantine: self.quarantine.clone(),
+                fresh_gen: self.fresh_gen,
+                retired: self.retired,
+                reserved: Reserved::default(),
+                kill_log: KillLog::default(),
+                layout: PhantomData,
+            },
+        }
+    }
+
+    /// Returns the allocator to the state it was in when the checkpoint was taken.
+    /// Any reservations made since are discarded, as are logged kills that come after the checkpoint.
+    pub fn restore(&mut self, checkpoint: &UntypedCheckpoint<L>) {
+        let mut kill_log = std::mem::take(&mut self.kill_log);
+        kill_log.rewind(&checkpoint.allocator.gen);
+
+        *self = UntypedAllocator {
+            kill_log,
+            ..checkpoint.allocator.clone()
+        };
+    }
+
+    /// Describes how the generation is out of sync, counting the batches it missed if they are logged
+    pub fn check_gen(&self, gen: &UntypedArenaGen) -> Result<(), OutOfSync> {
+        gen.compare(&self.gen).map_err(|error| match error {
+            OutOfSync::Behind { kills, .. } => OutOfSync::Behind {
+                kills,
+                batches: self.kill_log.batches_between(gen.epoch, self.gen.epoch),
+            },
+            error => error,
+        })
+    }
+
+    /// The Ids killed since the generation, oldest first
+    pub fn killed_since(&self, gen: &UntypedArenaGen) -> Result<Vec<UntypedId>, KilledSinceError> {
+        match gen.compare(&self.gen) {
+            Ok(()) => Ok(Vec::new()),
+            Err(OutOfSync::Behind { kills, .. }) => match self.kill_log.since(gen) {
+                Some(killed) => killed.map_err(KilledSinceError::OutOfSync),
+                None => Err(KilledSinceError::Truncated { kills }),
+            },
+            Err(error) => Err(KilledSinceError::OutOfSync(error)),
+        }
+    }
+
+    /// The indices whose entries differ from the checkpoint, skipping chunks that are still shared with it
+    pub(crate) fn changed_since(&self, checkpoint: &UntypedCheckpoint<L>) -> Vec<u32> {
+        let (entries, base) = (&self.entries, &checkpoint.allocator.entries);
+        let shared = |chunk: usize| match (entries.chunks().get(chunk), base.chunks().get(chunk)) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        };
+
+        // within a shared chunk, only the indices past the shorter end can differ
+        let common = entries.len().min(base.len());
+        let len = entries.len().max(base.len());
+
+        let mut changed = Vec::new();
+        for chunk in 0..len.div_ceil(CHUNK) {
+            let start = chunk * CHUNK;
+            let end = len.min(start + CHUNK);
+            let start = if shared(chunk) {
+                start.max(common)
+            } else {
+                start
+            };
+            let differs = (start..end).filter(|&index| entries.get(index) != base.get(index));
+            changed.extend(differs.map(|index| index as u32));
+        }
+        changed
+    }
+
+    fn quarantined_slots(&self) -> Vec<bool> {
+        let mut quarantined = vec![false; self.entries.len()];
+        for &(_, index) in &self.quarantine.slots {
+            quarantined[index as usize] = true;
+        }
+        quarantined
+    }
 }
 
 #[repr(transparent)]
-#[derive(Debug, ForceDefault, RefCast)]
-pub struct Allocator<Arena> {
-    untyped: UntypedAllocator,
-    arena: PhantomData<Arena>,
+#[derive(Debug, ForceDefault, ForceClone, RefCast)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent, bound = "L: IdLayout")
+)]
+pub struct Allocator<Arena, L = Split32x32> {
+    pub(crate) untyped: UntypedAllocator<L>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) arena: PhantomData<fn() -> Arena>,
 }
 
-impl<Arena> Allocator<Arena> {
+impl<Arena, L: IdLayout> Allocator<Arena, L> {
+    #[inline]
+    pub fn with_gen_exhaustion(self, exhaustion: GenExhaustion) -> Self {
+        Self {
+            untyped: self.untyped.with_gen_exhaustion(exhaustion),
+            arena: PhantomData,
+        }
+    }
+
+    /// Dead slots are carried over to the new policy
+    pub fn with_slot_reuse(self, reuse: SlotReuse) -> Self {
+        Self {
+            untyped: self.untyped.with_slot_reuse(reuse),
+            arena: PhantomData,
+        }
+    }
+
+    /// Killed slots are not reused until `advance_tick` has been called `ticks` times
+    #[inline]
+    pub fn with_quarantine(self, ticks: u64) -> Self {
+        Self {
+            untyped: self.untyped.with_quarantine(ticks),
+            arena: PhantomData,
+        }
+    }
+
+    /// Keeps the most recent `batches` batches of kills for `killed_since`
+    #[inline]
+    pub fn with_kill_log(self, batches: usize) -> Self {
+        Self {
+            untyped: self.untyped.with_kill_log(batches),
+            arena: PhantomData,
+        }
+    }
+
+    /// Releases any quarantined slots whose delay has passed
+    #[inline]
+    pub fn advance_tick(&mut self) {
+        self.untyped.advance_tick();
+    }
+
+    #[inline]
+    pub fn tick(&self) -> u64 {
+        self.untyped.tick()
+    }
+
+    /// The number of killed slots that are waiting to be released by `advance_tick`
+    #[inline]
+    pub fn quarantined(&self) -> usize {
+        self.untyped.quarantined()
+    }
+
+    /// The number of slots that have been permanently retired due to generation exhaustion
+    #[inline]
+    pub fn retired(&self) -> usize {
+        self.untyped.retired()
+    }
+
+    /// Panics if the index space of the layout is exhausted
     #[inline]
-    pub fn create(&mut self) -> Valid<Id<Arena>> {
+    pub fn create(&mut self) -> Valid<Id<Arena, L>> {
         Valid::new(self.create_id())
     }
 
+    /// Returns `None` if the index space of the layout is exhausted
     #[inline]
-    fn create_id(&mut self) -> Id<Arena> {
+    pub fn try_create(&mut self) -> Option<Valid<Id<Arena, L>>> {
+        self.untyped.try_create().map(Id::new).map(Valid::new)
+    }
+
+    /// Hands out an Id that becomes alive at the next `flush` or mutating call.
+    /// Panics if the index space of the layout is exhausted.
+    #[inline]
+    pub fn reserve(&self) -> Id<Arena, L> {
+        Id::new(self.untyped.reserve())
+    }
+
+    /// Returns `None` if the index space of the layout is exhausted
+    #[inline]
+    pub fn try_reserve(&self) -> Option<Id<Arena, L>> {
+        self.untyped.try_reserve().map(Id::new)
+    }
+
+    /// Makes reserved Ids alive
+    #[inline]
+    pub fn flush(&mut self) {
+        self.untyped.flush();
+    }
+
+    #[inline]
+    pub(crate) fn create_id(&mut self) -> Id<Arena, L> {
         Id::new(self.untyped.create())
     }
 
+    /// Logs the kill as a batch of its own if the allocator keeps a kill log
     #[inline]
-    pub fn kill(&mut self, id: Id<Arena>) -> bool {
-        self.untyped.kill(id.untyped)
+    pub fn kill(&mut self, id: Id<Arena, L>) -> bool {
+        self.untyped.kill(id.untyped())
     }
 
     /// Drains the Vec, kills all the Ids, and filters out any duplicate or invalid Ids
     /// Returns a Killed type for the purpose of notifying other arenas of their deletion
     #[inline]
     #[must_use]
-    pub fn kill_multiple(&mut self, ids: &mut Vec<Id<Arena>>) -> Killed<Arena> {
+    pub fn kill_multiple(&mut self, ids: &mut Vec<Id<Arena, L>>) -> Killed<Arena, L> {
         // Take gen value before any Ids are killed
         let start = AllocGen::new(self.untyped.gen.clone());
 
         // Filters out dead Ids and any duplicate values
-        let ids = ids.drain(..).filter(|id| self.kill(*id)).collect();
+        let ids = self
+            .untyped
+            .kill_batch(ids.drain(..).map(Id::untyped))
+            .into_iter()
+            .map(Id::new)
+            .collect();
 
         // Take gen value after Ids are killed
         let end = AllocGen::new(self.untyped.gen.clone());
 
-        Killed {
-            ids: Valid::new(ids),
-            before: start,
-            after: end,
-        }
+        Killed::new(ids, start, end, &self.untyped.kill_log)
     }
 
     #[inline]
-    pub fn is_alive(&self, id: Id<Arena>) -> bool {
-        self.untyped.is_alive(id.untyped)
+    pub fn is_alive(&self, id: Id<Arena, L>) -> bool {
+        self.untyped.is_alive(id.untyped())
     }
 
     #[inline]
-    pub fn create_only<'valid>(&'valid mut self) -> &mut CreateOnly<'valid, Arena> {
+    pub fn create_only<'valid>(&'valid mut self) -> &mut CreateOnly<'valid, Arena, L> {
         RefCast::ref_cast_mut(self)
     }
 
     #[inline]
-    pub fn validate(&self, id: Id<Arena>) -> Option<Valid<Id<Arena>>> {
+    pub fn validate(&self, id: Id<Arena, L>) -> Option<Valid<Id<Arena, L>>> {
         Validator::validate(&self, id)
     }
 
     #[inline]
-    pub fn ids<'valid>(&'valid self) -> impl Iterator<Item = Valid<'valid, Id<Arena>>> + '_ {
+    pub fn ids<'valid>(&'valid self) -> impl Iterator<Item = Valid<'valid, Id<Arena, L>>> + '_ {
         self.untyped
             .entries
             .iter()
@@ -181,17 +991,129 @@ impl<Arena> Allocator<Arena> {
             .map(Id::new)
             .map(Valid::new)
     }
+
+    /// The number of slots, which is one more than the highest index in use
+    #[inline]
+    pub fn slots(&self) -> usize {
+        self.untyped.slots()
+    }
+
+    /// Creates a new Id for each living Id in `other`, in order of index.
+    /// Panics if the index space of the layout is exhausted.
+    #[inline]
+    pub fn import_from(&mut self, other: &Allocator<Arena, L>) -> IdRemap<Arena, L> {
+        IdRemap::new(self.untyped.import_from(&other.untyped))
+    }
+
+    #[inline]
+    pub fn checkpoint(&mut self) -> Checkpoint<Arena, L> {
+        Checkpoint::new(self.untyped.checkpoint())
+    }
+
+    /// Returns the allocator to the state it was in when the checkpoint was taken, including its `AllocGen`
+    #[inline]
+    pub fn restore(&mut self, checkpoint: &Checkpoint<Arena, L>) {
+        self.untyped.restore(checkpoint.untyped());
+    }
+
+    /// Describes how the generation is out of sync, counting the batches it missed if they are logged
+    #[inline]
+    pub fn check_gen(&self, gen: &ArenaGen<Arena>) -> Result<(), OutOfSync> {
+        self.untyped.check_gen(gen.untyped())
+    }
+
+    /// Every Id killed since the generation as a single batch, if the kill log reaches back to it
+    pub fn killed_since(
+        &self,
+        gen: &ArenaGen<Arena>,
+    ) -> Result<Killed<'_, Arena, L>, KilledSinceError> {
+        let gen = gen.untyped();
+        let ids = self.untyped.killed_since(gen)?;
+        let before = UntypedAllocGen {
+            epoch: gen.epoch,
+            checksum: gen.checksum,
+        };
+
+        Ok(Killed::new(
+            ids.into_iter().map(Id::new).collect(),
+            AllocGen::new(before),
+            AllocGen::new(self.untyped.gen.clone()),
+            &self.untyped.kill_log,
+        ))
+    }
+
+    /// Returns a child allocator that starts from the current state, which can later be committed back.
+    /// The child shares its entries with the parent until either side writes to them.
+    #[inline]
+    pub fn fork(&mut self) -> Fork<Arena, L> {
+        Fork::new(self)
+    }
+
+    /// Replaces this allocator with the child, as long as this allocator has not changed since the fork.
+    /// Any change here since the fork is a conflict, even to indices the child left alone,
+    /// and the child is given back so it can be retried.
+    pub fn commit(&mut self, mut child: Fork<Arena, L>) -> Result<(), ForkConflict<Arena, L>> {
+        self.flush();
+        child.allocator_mut().flush();
+
+        let base = child.base();
+        let changed = self.untyped.changed_since(base);
+        let base = &base.allocator;
+        if !changed.is_empty() || self.untyped.gen != base.gen || self.tick() != base.tick() {
+            return Err(ForkConflict::new(changed, child));
+        }
+
+        let (allocator, _) = child.into_parts();
+        *self = allocator;
+        Ok(())
+    }
+
+    /// Shares the allocator read-only without copying its entries
+    #[inline]
+    pub fn freeze(self) -> FrozenAllocator<Arena, L> {
+        FrozenAllocator::from(self)
+    }
+}
+
+impl<Arena: Dynamic, L: IdLayout> Allocator<Arena, L> {
+    /// Moves living entries down into dead slots and truncates the dead slots left at the end.
+    /// Each moved Id is killed, and the remap gives the Id that replaces it.
+    /// Apply the remap to any storage before handling the Killed Ids.
+    #[must_use]
+    pub fn compact(&mut self) -> (IdRemap<Arena, L>, Killed<'_, Arena, L>) {
+        let before = AllocGen::new(self.untyped.gen.clone());
+        let remap = self.untyped.compact();
+        let after = AllocGen::new(self.untyped.gen.clone());
+
+        let ids = remap.iter().map(|(old, _)| Id::new(old)).collect();
+        let killed = Killed::new(ids, before, after, &self.untyped.kill_log);
+
+        (IdRemap::new(remap), killed)
+    }
+
+    /// Truncates the dead slots at the end without moving any living entries
+    #[inline]
+    pub fn trim(&mut self) {
+        self.untyped.trim();
+    }
 }
 
-impl<Arena: Fixed> Allocator<Arena> {
+impl<Arena: Fixed, L: IdLayout> Allocator<Arena, L> {
+    /// Panics if the index space of the layout is exhausted
     #[inline]
-    pub fn create_range(&mut self, len: usize) -> IdRange<Arena> {
+    pub fn create_range(&mut self, len: usize) -> IdRange<Arena, L> {
         let range = self.untyped.create_range(len);
         IdRange::from(range)
     }
+
+    /// Returns `None` if the index space of the layout is exhausted
+    #[inline]
+    pub fn try_create_range(&mut self, len: usize) -> Option<IdRange<Arena, L>> {
+        self.untyped.try_create_range(len).map(IdRange::from)
+    }
 }
 
-impl<Arena> AsRef<AllocGen<Arena>> for Allocator<Arena> {
+impl<Arena, L> AsRef<AllocGen<Arena>> for Allocator<Arena, L> {
     #[inline]
     fn as_ref(&self) -> &AllocGen<Arena> {
         RefCast::ref_cast(&self.untyped.gen)
@@ -200,9 +1122,9 @@ impl<Arena> AsRef<AllocGen<Arena>> for Allocator<Arena> {
 
 // Must implement for an Allocator reference so that there is a lifetime
 // for the resulting value to inherit
-impl<'valid, Arena> Validator<'valid, Arena> for &'valid Allocator<Arena> {
+impl<'valid, Arena, L: IdLayout> Validator<'valid, Arena, L> for &'valid Allocator<Arena, L> {
     #[inline]
-    fn validate(&self, id: Id<Arena>) -> Option<Valid<'valid, Id<Arena>>> {
+    fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'valid, Id<Arena, L>>> {
         if self.is_alive(id) {
             Some(Valid::new(id))
         } else {
@@ -211,9 +1133,9 @@ impl<'valid, Arena> Validator<'valid, Arena> for &'valid Allocator<Arena> {
     }
 }
 
-impl<'valid, Arena> Validator<'valid, Arena> for &'valid mut Allocator<Arena> {
+impl<'valid, Arena, L: IdLayout> Validator<'valid, Arena, L> for &'valid mut Allocator<Arena, L> {
     #[inline]
-    fn validate(&self, id: Id<Arena>) -> Option<Valid<'valid, Id<Arena>>> {
+    fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'valid, Id<Arena, L>>> {
         if self.is_alive(id) {
             Some(Valid::new(id))
         } else {
@@ -224,24 +1146,24 @@ impl<'valid, Arena> Validator<'valid, Arena> for &'valid mut Allocator<Arena> {
 
 #[repr(transparent)]
 #[derive(Debug, RefCast)]
-pub struct CreateOnly<'valid, Arena> {
-    allocator: Allocator<Arena>,
+pub struct CreateOnly<'valid, Arena, L = Split32x32> {
+    allocator: Allocator<Arena, L>,
     valid: PhantomData<&'valid ()>,
 }
 
-impl<'valid, Arena> CreateOnly<'valid, Arena> {
+impl<'valid, Arena, L: IdLayout> CreateOnly<'valid, Arena, L> {
     #[inline]
-    pub fn create(&mut self) -> Valid<'valid, Id<Arena>> {
+    pub fn create(&mut self) -> Valid<'valid, Id<Arena, L>> {
         Valid::new(self.allocator.create_id())
     }
 
     #[inline]
-    pub fn is_alive(&self, id: Id<Arena>) -> bool {
+    pub fn is_alive(&self, id: Id<Arena, L>) -> bool {
         self.allocator.is_alive(id)
     }
 
     #[inline]
-    pub fn ids(&self) -> impl Iterator<Item = Valid<'valid, Id<Arena>>> + '_ {
+    pub fn ids(&self) -> impl Iterator<Item = Valid<'valid, Id<Arena, L>>> + '_ {
         self.allocator
             .untyped
             .entries
@@ -252,23 +1174,25 @@ impl<'valid, Arena> CreateOnly<'valid, Arena> {
     }
 }
 
-impl<Arena> AsRef<AllocGen<Arena>> for CreateOnly<'_, Arena> {
+impl<Arena, L> AsRef<AllocGen<Arena>> for CreateOnly<'_, Arena, L> {
     #[inline]
     fn as_ref(&self) -> &AllocGen<Arena> {
         self.allocator.as_ref()
     }
 }
 
-impl<'valid, Arena> Validator<'valid, Arena> for CreateOnly<'valid, Arena> {
+impl<'valid, Arena, L: IdLayout> Validator<'valid, Arena, L> for CreateOnly<'valid, Arena, L> {
     #[inline]
-    fn validate(&self, id: Id<Arena>) -> Option<Valid<'valid, Id<Arena>>> {
+    fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'valid, Id<Arena, L>>> {
         self.is_alive(id).then(|| Valid::new(id))
     }
 }
 
-impl<'a, 'valid, Arena> Validator<'valid, Arena> for &'a mut CreateOnly<'valid, Arena> {
+impl<'a, 'valid, Arena, L: IdLayout> Validator<'valid, Arena, L>
+    for &'a mut CreateOnly<'valid, Arena, L>
+{
     #[inline]
-    fn validate(&self, id: Id<Arena>) -> Option<Valid<'valid, Id<Arena>>> {
+    fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'valid, Id<Arena, L>>> {
         self.is_alive(id).then(|| Valid::new(id))
     }
 }
@@ -276,13 +1200,30 @@ impl<'a, 'valid, Arena> Validator<'valid, Arena> for &'a mut CreateOnly<'valid,
 /// A list of valid, unique Ids that have been killed.
 /// Includes before and after allocator generations for validating and updating ArenaGen values  
 #[derive(Debug)]
-pub struct Killed<'v, Arena> {
-    ids: Valid<'v, Vec<Id<Arena>>>,
+pub struct Killed<'v, Arena, L: IdLayout = Split32x32> {
+    ids: Valid<'v, Vec<Id<Arena, L>>>,
     before: AllocGen<Arena>,
     after: AllocGen<Arena>,
+    /// The log of the allocator the batch came from, for counting the batches a storage missed
+    log: &'v KillLog,
 }
 
-impl<'v, Arena> Killed<'v, Arena> {
+impl<'v, Arena, L: IdLayout> Killed<'v, Arena, L> {
+    #[inline]
+    pub(crate) fn new(
+        ids: Vec<Id<Arena, L>>,
+        before: AllocGen<Arena>,
+        after: AllocGen<Arena>,
+        log: &'v KillLog,
+    ) -> Self {
+        Self {
+            ids: Valid::new(ids),
+            before,
+            after,
+            log,
+        }
+    }
+
     pub fn before(&self) -> &AllocGen<Arena> {
         &self.before
     }
@@ -291,18 +1232,33 @@ impl<'v, Arena> Killed<'v, Arena> {
         &self.after
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = Valid<'v, &Id<Arena>>> {
+    pub fn iter(&self) -> impl Iterator<Item = Valid<'v, &Id<Arena, L>>> {
         (&self.ids).into_iter()
     }
 
     pub fn update_gen(&self, gen: &mut ArenaGen<Arena>) {
-        gen.update(&self.before, &self.after);
+        if let Err(error) = self.try_update_gen(gen) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Updates the generation if it has seen every earlier batch, otherwise describes how it is out of sync
+    pub fn try_update_gen(&self, gen: &mut ArenaGen<Arena>) -> Result<(), OutOfSync> {
+        let epoch = gen.untyped().epoch;
+        gen.try_update(&self.before, &self.after)
+            .map_err(|error| match error {
+                OutOfSync::Behind { kills, .. } => OutOfSync::Behind {
+                    kills,
+                    batches: self.log.batches_between(epoch, self.before.untyped().epoch),
+                },
+                error => error,
+            })
     }
 }
 
-impl<'a, 'v, Arena> IntoIterator for &'a Killed<'v, Arena> {
-    type Item = Valid<'v, &'a Id<Arena>>;
-    type IntoIter = crate::valid::ValidIter<'v, std::slice::Iter<'a, Id<Arena>>>;
+impl<'a, 'v, Arena, L: IdLayout> IntoIterator for &'a Killed<'v, Arena, L> {
+    type Item = Valid<'v, &'a Id<Arena, L>>;
+    type IntoIter = crate::valid::ValidIter<'v, std::slice::Iter<'a, Id<Arena, L>>>;
 
     fn into_iter(self) -> Self::IntoIter {
         (&self.ids).into_iter()
@@ -310,18 +1266,242 @@ impl<'a, 'v, Arena> IntoIterator for &'a Killed<'v, Arena> {
 }
 
 #[derive(Debug, ForceDefault, ForceClone)]
-pub struct RangeAllocator<Arena> {
+pub struct RangeAllocator<Arena, L = Split32x32> {
     next: usize,
-    arena: PhantomData<*const Arena>,
+    arena: PhantomData<fn() -> (Arena, L)>,
 }
 
-impl<Arena: Fixed> RangeAllocator<Arena> {
+impl<Arena: Fixed, L: IdLayout> RangeAllocator<Arena, L> {
+    /// Panics if the index space of the layout is exhausted
     #[inline]
-    pub fn create(&mut self, len: usize) -> IdRange<Arena> {
+    pub fn create(&mut self, len: usize) -> IdRange<Arena, L> {
+        self.try_create(len).expect("Id index space exhausted")
+    }
+
+    /// Returns `None` if the index space of the layout is exhausted
+    #[inline]
+    pub fn try_create(&mut self, len: usize) -> Option<IdRange<Arena, L>> {
         let start = self.next;
-        self.next += len;
-        let end = self.next;
-        IdRange::new(start, end)
+        let end = start
+            .checked_add(len)
+            .filter(|&end| end <= L::MAX_INDEX as usize + 1)?;
+        self.next = end;
+        Some(IdRange::new(start, end))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::*;
+    use std::convert::TryFrom;
+    use std::fmt::{Display, Formatter};
+
+    /// Unvalidated allocator state, checked before it is turned into an `UntypedAllocator`
+    #[derive(serde::Deserialize)]
+    #[serde(bound = "")]
+    pub(super) struct UntypedAllocatorData<L> {
+        entries: Vec<Entry>,
+        free: FreeList,
+        gen: UntypedAllocGen,
+        exhaustion: GenExhaustion,
+        quarantine: Quarantine,
+        #[serde(default)]
+        fresh_gen: Gen,
+        #[serde(default)]
+        reserved: Reserved,
+        #[serde(default)]
+        kill_log: KillLog,
+        #[serde(skip)]
+        layout: PhantomData<L>,
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub(super) enum InvalidAllocator {
+        MisplacedId { index: usize, id: UntypedId },
+        DoesNotFit(usize),
+        OutOfBounds(u32),
+        LinksToLiving(u32),
+        LinksToRetired(u32),
+        Repeated(u32),
+        Inconsistent(SlotReuse),
+        FutureTick(u64),
+        FreshGen(u32),
+        ReservedOutsideFreeList(u32),
+        DetachedKillLog,
+    }
+
+    impl Display for InvalidAllocator {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                InvalidAllocator::MisplacedId { index, id } => {
+                    write!(f, "entry {} contains an id with index {}", index, id.index)
+                }
+                InvalidAllocator::DoesNotFit(index) => {
+                    write!(f, "entry {} does not fit in the Id layout", index)
+                }
+                InvalidAllocator::OutOfBounds(index) => {
+                    write!(f, "free list index {} is out of bounds", index)
+                }
+                InvalidAllocator::LinksToLiving(index) => {
+                    write!(f, "free list links to living entry {}", index)
+                }
+                InvalidAllocator::LinksToRetired(index) => {
+                    write!(f, "free list links to retired entry {}", index)
+                }
+                InvalidAllocator::Repeated(index) => {
+                    write!(f, "free list contains entry {} more than once", index)
+                }
+                InvalidAllocator::Inconsistent(reuse) => {
+                    write!(f, "free list is inconsistent with {:?} reuse", reuse)
+                }
+                InvalidAllocator::FutureTick(tick) => {
+                    write!(f, "slot quarantined at future tick {}", tick)
+                }
+                InvalidAllocator::FreshGen(gen) => {
+                    write!(f, "fresh generation {} does not fit in the Id layout", gen)
+                }
+                InvalidAllocator::ReservedOutsideFreeList(index) => {
+                    write!(f, "reserved entry {} is not in the free list", index)
+                }
+                InvalidAllocator::DetachedKillLog => {
+                    write!(f, "kill log does not lead up to the allocator generation")
+                }
+            }
+        }
+    }
+
+    impl<L: IdLayout> TryFrom<UntypedAllocatorData<L>> for UntypedAllocator<L> {
+        type Error = InvalidAllocator;
+
+        fn try_from(data: UntypedAllocatorData<L>) -> Result<Self, Self::Error> {
+            let UntypedAllocatorData {
+                entries,
+                free,
+                gen,
+                exhaustion,
+                quarantine,
+                fresh_gen,
+                mut reserved,
+                kill_log,
+                ..
+            } = data;
+
+            if fresh_gen.get() > L::MAX_GEN {
+                return Err(InvalidAllocator::FreshGen(fresh_gen.get()));
+            }
+
+            let past_end = *reserved.past_end.get_mut();
+            if past_end > 0 && entries.len() + past_end > L::MAX_INDEX as usize + 1 {
+                return Err(InvalidAllocator::DoesNotFit(entries.len() + past_end - 1));
+            }
+
+            let next_dead = match *reserved.next_dead.get_mut() {
+                0 | RESERVED_ALL => None,
+                index => Some((index - 1) as u32),
+            };
+            let mut reaches_reserved = false;
+
+            for (index, entry) in entries.iter().enumerate() {
+                let fits = match entry {
+                    Entry::Alive(id) if id.index() != index => {
+                        return Err(InvalidAllocator::MisplacedId { index, id: *id });
+                    }
+                    Entry::Alive(id) => L::fits(*id),
+                    Entry::Dead(dead) => {
+                        index <= L::MAX_INDEX as usize && dead.gen.get() <= L::MAX_GEN
+                    }
+                    Entry::Retired => index <= L::MAX_INDEX as usize,
+                };
+
+                if !fits {
+                    return Err(InvalidAllocator::DoesNotFit(index));
+                }
+            }
+
+            let mut visited = vec![false; entries.len()];
+            let mut visit = |index: u32| {
+                let dead = match entries.get(index as usize) {
+                    Some(Entry::Dead(dead)) => dead,
+                    Some(Entry::Alive(_)) => return Err(InvalidAllocator::LinksToLiving(index)),
+                    Some(Entry::Retired) => return Err(InvalidAllocator::LinksToRetired(index)),
+                    None => return Err(InvalidAllocator::OutOfBounds(index)),
+                };
+
+                let visited = &mut visited[index as usize];
+                if *visited {
+                    return Err(InvalidAllocator::Repeated(index));
+                }
+                *visited = true;
+
+                Ok(dead)
+            };
+
+            let consistent = match free.reuse {
+                SlotReuse::Lifo | SlotReuse::Fifo => {
+                    let mut last = None;
+                    let mut next = free.head;
+                    while let Some(index) = next {
+                        next = visit(index.get())?.next_dead;
+                        last = Some(index);
+                        reaches_reserved |= next_dead == Some(index.get());
+                    }
+
+                    let tail = match free.reuse {
+                        SlotReuse::Fifo => last,
+                        _ => None,
+                    };
+
+                    free.tail == tail && free.lowest.is_empty()
+                }
+                SlotReuse::LowestIndex => {
+                    for &Reverse(index) in &free.lowest {
+                        visit(index)?;
+                    }
+
+                    free.head.is_none() && free.tail.is_none()
+                }
+            };
+
+            if !consistent {
+                return Err(InvalidAllocator::Inconsistent(free.reuse));
+            }
+
+            match next_dead {
+                Some(index) if !reaches_reserved => {
+                    return Err(InvalidAllocator::ReservedOutsideFreeList(index));
+                }
+                _ => {}
+            }
+
+            if kill_log.batches.len() > kill_log.capacity || !kill_log.leads_to(&gen) {
+                return Err(InvalidAllocator::DetachedKillLog);
+            }
+
+            for &(tick, index) in &quarantine.slots {
+                if tick > quarantine.tick {
+                    return Err(InvalidAllocator::FutureTick(tick));
+                }
+                visit(index)?;
+            }
+
+            let retired = entries
+                .iter()
+                .filter(|entry| matches!(entry, Entry::Retired))
+                .count();
+
+            Ok(UntypedAllocator {
+                entries: Entries::from(entries),
+                free,
+                gen,
+                exhaustion,
+                quarantine,
+                fresh_gen,
+                retired,
+                reserved,
+                kill_log,
+                layout: PhantomData,
+            })
+        }
     }
 }
 
@@ -407,6 +1587,361 @@ mod tests {
         assert_eq!(2, id2.gen().get());
     }
 
+    fn exhausted_allocator(exhaustion: GenExhaustion) -> (Allocator<()>, Id<()>) {
+        let mut allocator = Allocator::<()>::default().with_gen_exhaustion(exhaustion);
+        let id = allocator.create_id();
+
+        let id = Id::new(UntypedId {
+            index: id.untyped().index,
+            gen: Gen::new(u32::MAX).unwrap(),
+        });
+        allocator.untyped.entries[id.index()] = Entry::Alive(id.untyped());
+
+        (allocator, id)
+    }
+
+    #[test]
+    fn exhausted_gen_retire() {
+        let (mut allocator, id) = exhausted_allocator(GenExhaustion::Retire);
+
+        assert!(allocator.kill(id));
+        assert_eq!(1, allocator.retired());
+
+        let new = allocator.create_id();
+        assert_eq!(1, new.index());
+        assert!(!allocator.is_alive(id));
+    }
+
+    #[test]
+    fn exhausted_gen_wrap() {
+        let (mut allocator, id) = exhausted_allocator(GenExhaustion::Wrap);
+
+        assert!(allocator.kill(id));
+        assert_eq!(0, allocator.retired());
+
+        let new = allocator.create_id();
+        assert_eq!(0, new.index());
+        assert_eq!(1, new.gen().get());
+    }
+
+    #[test]
+    #[should_panic]
+    fn exhausted_gen_panic() {
+        let (mut allocator, id) = exhausted_allocator(GenExhaustion::Panic);
+
+        allocator.kill(id);
+    }
+
+    fn compact_exhausted(exhaustion: GenExhaustion) -> Allocator<()> {
+        let (mut allocator, id) = exhausted_allocator(exhaustion);
+        let hole = Id::new(UntypedId::first(0));
+        allocator.untyped.entries[0] = Entry::Alive(hole.untyped());
+        allocator.untyped.entries.push(Entry::Alive(UntypedId {
+            index: 1,
+            ..id.untyped()
+        }));
+        allocator.kill(hole);
+
+        allocator.untyped.compact();
+        allocator
+    }
+
+    #[test]
+    fn compact_follows_exhaustion_policy() {
+        let allocator = compact_exhausted(GenExhaustion::Retire);
+        assert_eq!((2, 1), (allocator.slots(), allocator.retired()));
+
+        let allocator = compact_exhausted(GenExhaustion::Wrap);
+        assert_eq!((1, 0), (allocator.slots(), allocator.retired()));
+    }
+
+    #[test]
+    #[should_panic(expected = "generation exhausted")]
+    fn compact_exhausted_gen_panic() {
+        compact_exhausted(GenExhaustion::Panic);
+    }
+
+    #[test]
+    fn packed_index_overflow() {
+        use crate::layout::Packed16x16;
+
+        let mut allocator = Allocator::<(), Packed16x16>::default();
+        for _ in 0..=u16::MAX {
+            allocator.create();
+        }
+
+        assert!(allocator.try_create().is_none());
+    }
+
+    #[test]
+    fn packed_gen_exhaustion() {
+        use crate::layout::Packed16x16;
+
+        let mut allocator =
+            Allocator::<(), Packed16x16>::default().with_gen_exhaustion(GenExhaustion::Retire);
+        for _ in 0..u16::MAX {
+            let id = allocator.create_id();
+            assert_eq!(0, id.index());
+            allocator.kill(id);
+        }
+
+        assert_eq!(1, allocator.retired());
+        assert_eq!(1, allocator.create_id().index());
+    }
+
+    fn reuse_order(reuse: SlotReuse) -> Vec<usize> {
+        let mut allocator = Allocator::<()>::default().with_slot_reuse(reuse);
+        let ids = (0..4).map(|_| allocator.create_id()).collect::<Vec<_>>();
+
+        allocator.kill(ids[2]);
+        allocator.kill(ids[0]);
+        allocator.kill(ids[3]);
+
+        (0..4).map(|_| allocator.create_id().index()).collect()
+    }
+
+    #[test]
+    fn slot_reuse_lifo() {
+        assert_eq!(vec![3, 0, 2, 4], reuse_order(SlotReuse::Lifo));
+    }
+
+    #[test]
+    fn slot_reuse_fifo() {
+        assert_eq!(vec![2, 0, 3, 4], reuse_order(SlotReuse::Fifo));
+    }
+
+    #[test]
+    fn slot_reuse_lowest_index() {
+        assert_eq!(vec![0, 2, 3, 4], reuse_order(SlotReuse::LowestIndex));
+    }
+
+    #[test]
+    fn with_slot_reuse_keeps_dead_slots() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..3).map(|_| allocator.create_id()).collect::<Vec<_>>();
+        allocator.kill(ids[2]);
+        allocator.kill(ids[1]);
+
+        let mut allocator = allocator.with_slot_reuse(SlotReuse::LowestIndex);
+
+        let id = allocator.create_id();
+        assert_eq!(1, id.index());
+        assert_eq!(2, id.gen().get());
+        assert_eq!(2, allocator.create_id().index());
+        assert_eq!(3, allocator.create_id().index());
+    }
+
+    #[test]
+    fn quarantine_delays_reuse() {
+        let mut allocator = Allocator::<()>::default().with_quarantine(2);
+        let id = allocator.create_id();
+        allocator.kill(id);
+
+        assert_eq!(1, allocator.quarantined());
+        assert!(!allocator.is_alive(id));
+        assert_eq!(1, allocator.create_id().index());
+
+        allocator.advance_tick();
+        assert_eq!(2, allocator.create_id().index());
+
+        allocator.advance_tick();
+        assert_eq!(0, allocator.quarantined());
+
+        let reused = allocator.create_id();
+        assert_eq!(0, reused.index());
+        assert_eq!(2, reused.gen().get());
+    }
+
+    #[test]
+    fn quarantine_releases_in_kill_order() {
+        let mut allocator = Allocator::<()>::default().with_quarantine(1);
+        let ids = (0..3).map(|_| allocator.create_id()).collect::<Vec<_>>();
+
+        allocator.kill(ids[1]);
+        allocator.advance_tick();
+        allocator.kill(ids[0]);
+
+        assert_eq!(1, allocator.create_id().index());
+        assert_eq!(3, allocator.create_id().index());
+
+        allocator.advance_tick();
+        assert_eq!(0, allocator.create_id().index());
+    }
+
+    #[derive(Debug)]
+    struct Compact;
+    crate::dynamic_id!(Compact);
+
+    #[test]
+    fn compact_moves_living_into_holes() {
+        let mut allocator = Allocator::<Compact>::default();
+        let ids = (0..6).map(|_| allocator.create_id()).collect::<Vec<_>>();
+        allocator.kill(ids[0]);
+        allocator.kill(ids[2]);
+        allocator.kill(ids[3]);
+
+        let (remap, killed) = allocator.compact();
+        assert_eq!(2, killed.iter().count());
+
+        assert_eq!(3, allocator.slots());
+        assert_eq!(2, remap.len());
+
+        let moved = remap.get(ids[5]).unwrap();
+        assert_eq!(0, moved.index());
+        assert_eq!(2, moved.gen().get());
+        assert_eq!(2, remap.get(ids[4]).unwrap().index());
+        assert_eq!(None, remap.get(ids[1]));
+
+        assert!(allocator.is_alive(ids[1]));
+        assert!(!allocator.is_alive(ids[4]));
+        assert!(!allocator.is_alive(ids[5]));
+        assert!(allocator.ids().all(|id| id.value.index() < 3));
+    }
+
+    #[test]
+    fn compact_does_not_reissue_truncated_ids() {
+        let mut allocator = Allocator::<Compact>::default();
+        let ids = (0..3).map(|_| allocator.create_id()).collect::<Vec<_>>();
+        allocator.kill(ids[0]);
+
+        let (remap, _) = allocator.compact();
+        assert_eq!(2, allocator.slots());
+        assert_eq!(Some(0), remap.get(ids[2]).map(Id::index));
+
+        let id = allocator.create_id();
+        assert_eq!(2, id.index());
+        assert_ne!(ids[2], id);
+        assert!(!allocator.is_alive(ids[2]));
+    }
+
+    #[test]
+    fn compact_skips_quarantined_slots() {
+        let mut allocator = Allocator::<Compact>::default().with_quarantine(1);
+        let ids = (0..3).map(|_| allocator.create_id()).collect::<Vec<_>>();
+        allocator.kill(ids[0]);
+
+        let (remap, _) = allocator.compact();
+
+        assert!(remap.is_empty());
+        assert_eq!(3, allocator.slots());
+    }
+
+    #[test]
+    fn compact_with_quarantine_truncates_moved_slots() {
+        let mut allocator = Allocator::<Compact>::default()
+            .with_quarantine(1)
+            .with_kill_log(4);
+        let ids = (0..4).map(|_| allocator.create_id()).collect::<Vec<_>>();
+        allocator.kill(ids[0]);
+        allocator.kill(ids[1]);
+        allocator.advance_tick();
+        let mut arena_gen = ArenaGen::<Compact>::default();
+        let killed = allocator.killed_since(&arena_gen).unwrap();
+        killed.update_gen(&mut arena_gen);
+
+        let moved = {
+            let (remap, killed) = allocator.compact();
+            assert_eq!(2, remap.len());
+            killed.iter().map(|id| *id.value).collect::<Vec<_>>()
+        };
+
+        assert_eq!(2, allocator.slots());
+        assert_eq!(2, allocator.ids().count());
+
+        // the moved-from slots are logged as a single batch
+        assert_eq!(
+            Err(OutOfSync::Behind {
+                kills: 2,
+                batches: Some(1)
+            }),
+            allocator.check_gen(&arena_gen)
+        );
+        let logged = allocator.killed_since(&arena_gen).unwrap();
+        assert_eq!(moved, logged.iter().map(|id| *id.value).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn trim_keeps_living_in_place() {
+        let mut allocator = Allocator::<Compact>::default();
+        let ids = (0..4).map(|_| allocator.create_id()).collect::<Vec<_>>();
+        allocator.kill(ids[0]);
+        allocator.kill(ids[2]);
+        allocator.kill(ids[3]);
+
+        allocator.trim();
+
+        assert_eq!(2, allocator.slots());
+        assert!(allocator.is_alive(ids[1]));
+        assert_eq!(0, allocator.create_id().index());
+
+        let id = allocator.create_id();
+        assert_eq!(2, id.index());
+        assert_eq!(2, id.gen().get());
+    }
+
+    #[test]
+    fn reserve_from_free_list_then_past_end() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..3).map(|_| allocator.create_id()).collect::<Vec<_>>();
+        allocator.kill(ids[0]);
+        allocator.kill(ids[2]);
+
+        let reserved = std::thread::scope(|scope| {
+            let allocator = &allocator;
+            let workers = (0..2)
+                .map(|_| {
+                    scope.spawn(move || (0..2).map(|_| allocator.reserve()).collect::<Vec<_>>())
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let mut indices = reserved.iter().map(|id| id.index()).collect::<Vec<_>>();
+        indices.sort_unstable();
+        assert_eq!(vec![0, 2, 3, 4], indices);
+        assert!(reserved.iter().all(|id| !allocator.is_alive(*id)));
+
+        allocator.flush();
+
+        assert!(reserved.iter().all(|id| allocator.is_alive(*id)));
+        assert_eq!(5, allocator.create_id().index());
+    }
+
+    #[test]
+    fn reserve_matches_create_order() {
+        let mut allocator = Allocator::<()>::default().with_slot_reuse(SlotReuse::Fifo);
+        let ids = (0..3).map(|_| allocator.create_id()).collect::<Vec<_>>();
+        allocator.kill(ids[1]);
+        allocator.kill(ids[0]);
+
+        let reserved = allocator.reserve();
+        assert_eq!(1, reserved.index());
+        assert_eq!(2, reserved.gen().get());
+
+        // mutating calls flush first
+        let created = allocator.create_id();
+        assert!(allocator.is_alive(reserved));
+        assert_eq!(0, created.index());
+    }
+
+    #[test]
+    fn reserve_lowest_index_past_end() {
+        let mut allocator = Allocator::<()>::default().with_slot_reuse(SlotReuse::LowestIndex);
+        let id = allocator.create_id();
+        allocator.kill(id);
+
+        let reserved = allocator.reserve();
+        assert_eq!(1, reserved.index());
+
+        allocator.flush();
+        assert!(allocator.is_alive(reserved));
+        assert_eq!(0, allocator.create_id().index());
+    }
+
     #[test]
     fn validate_allocator() {
         let mut allocator = Allocator::<()>::default();
@@ -420,6 +1955,144 @@ mod tests {
         dbg!(valid.value);
     }
 
+    #[test]
+    fn try_update_gen_after_missed_batch() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..3).map(|_| allocator.create().value).collect::<Vec<_>>();
+        let mut gen = ArenaGen::default();
+
+        let _ = allocator.kill_multiple(&mut vec![ids[0], ids[1]]);
+        let killed = allocator.kill_multiple(&mut vec![ids[2]]);
+
+        let behind = OutOfSync::Behind {
+            kills: 2,
+            batches: None,
+        };
+        assert_eq!(Err(behind), killed.try_update_gen(&mut gen));
+        assert!(gen != *killed.before());
+    }
+
+    #[test]
+    fn try_update_gen_counts_missed_batches_in_log() {
+        let mut allocator = Allocator::<()>::default().with_kill_log(4);
+        let ids = (0..4).map(|_| allocator.create().value).collect::<Vec<_>>();
+        let mut gen = ArenaGen::default();
+
+        let _ = allocator.kill_multiple(&mut vec![ids[0], ids[1]]);
+        allocator.kill(ids[2]);
+        let killed = allocator.kill_multiple(&mut vec![ids[3]]);
+
+        let behind = OutOfSync::Behind {
+            kills: 3,
+            batches: Some(2),
+        };
+        assert_eq!(Err(behind), killed.try_update_gen(&mut gen));
+    }
+
+    #[test]
+    #[should_panic(expected = "storage is behind the allocator by 2 kills in 2 batches")]
+    fn update_gen_panics_with_description() {
+        let mut allocator = Allocator::<()>::default().with_kill_log(4);
+        let ids = (0..3).map(|_| allocator.create().value).collect::<Vec<_>>();
+        allocator.kill(ids[0]);
+        allocator.kill(ids[1]);
+
+        let killed = allocator.kill_multiple(&mut vec![ids[2]]);
+        killed.update_gen(&mut ArenaGen::default());
+    }
+
+    #[test]
+    fn killed_since_catches_up_lagging_gen() {
+        let mut allocator = Allocator::<()>::default().with_kill_log(4);
+        let ids = (0..6).map(|_| allocator.create().value).collect::<Vec<_>>();
+        let mut gen = ArenaGen::default();
+
+        let _ = allocator.kill_multiple(&mut vec![ids[0], ids[1]]);
+        allocator.kill(ids[2]);
+        let _ = allocator.kill_multiple(&mut vec![ids[3], ids[3], ids[4]]);
+
+        let behind = OutOfSync::Behind {
+            kills: 5,
+            batches: Some(3),
+        };
+        assert_eq!(Err(behind), allocator.check_gen(&gen));
+
+        let killed = allocator.killed_since(&gen).unwrap();
+        assert_eq!(
+            ids[..5],
+            killed.iter().map(|id| *id.value).collect::<Vec<_>>()[..]
+        );
+        killed.update_gen(&mut gen);
+        assert!(gen == *allocator.as_ref());
+        assert_eq!(0, allocator.killed_since(&gen).unwrap().iter().count());
+
+        // a storage that counted kills one Id at a time can stop partway through a batch
+        let mut partial = ArenaGen::default();
+        for id in &ids[..4] {
+            partial.increment_gen(*id);
+        }
+        let killed = allocator.killed_since(&partial).unwrap();
+        assert_eq!(
+            vec![ids[4]],
+            killed.iter().map(|id| *id.value).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn killed_since_truncated_log() {
+        let mut allocator = Allocator::<()>::default().with_kill_log(2);
+        let ids = (0..3).map(|_| allocator.create().value).collect::<Vec<_>>();
+        let gen = ArenaGen::default();
+
+        for id in &ids {
+            allocator.kill(*id);
+        }
+
+        let truncated = KilledSinceError::Truncated { kills: 3 };
+        assert_eq!(truncated, allocator.killed_since(&gen).unwrap_err());
+        let behind = OutOfSync::Behind {
+            kills: 3,
+            batches: None,
+        };
+        assert_eq!(Err(behind), allocator.check_gen(&gen));
+
+        let mut diverged = ArenaGen::default();
+        for _ in 0..2 {
+            diverged.increment_gen(Id::<()>::first(10));
+        }
+        assert_eq!(
+            KilledSinceError::OutOfSync(OutOfSync::Diverged),
+            allocator.killed_since(&diverged).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn valid_ids_in_scoped_threads() {
+        let mut allocator = Allocator::<*const ()>::default();
+        let ids = (0..4).map(|_| allocator.create().value).collect::<Vec<_>>();
+        let allocator = &allocator;
+
+        let valid = std::thread::scope(|scope| {
+            let workers = ids
+                .chunks(2)
+                .map(|ids| {
+                    scope.spawn(move || {
+                        ids.iter()
+                            .filter_map(|id| allocator.validate(*id))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(ids, valid.iter().map(|id| id.value).collect::<Vec<_>>());
+    }
+
     #[test]
     fn validate_create_only() {
         let mut allocator = Allocator::<()>::default();
@@ -471,6 +2144,26 @@ mod tests {
         assert_eq!(2, range.len());
     }
 
+    #[test]
+    fn range_allocator_index_overflow() {
+        use crate::layout::Packed16x16;
+
+        #[derive(Debug, Copy, Clone)]
+        struct Fixed;
+        crate::fixed_id!(Fixed);
+
+        let mut allocator = RangeAllocator::<Fixed, Packed16x16>::default();
+        assert!(allocator.try_create(u16::MAX as usize + 2).is_none());
+
+        let range = allocator.create(u16::MAX as usize);
+        assert_eq!(
+            Some(u16::MAX as usize),
+            range.into_iter().last().map(|id| id.index() + 1)
+        );
+        assert_eq!(1, allocator.create(1).len());
+        assert!(allocator.try_create(1).is_none());
+    }
+
     #[test]
     fn kill_vec_given_live() {
         #[derive(Debug)]
@@ -540,4 +2233,180 @@ mod tests {
 
         dbg!(killed);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        for &reuse in &[SlotReuse::Lifo, SlotReuse::Fifo, SlotReuse::LowestIndex] {
+            let mut allocator = Allocator::<()>::default().with_slot_reuse(reuse);
+            let ids = (0..5).map(|_| allocator.create_id()).collect::<Vec<_>>();
+            allocator.kill(ids[1]);
+            allocator.kill(ids[3]);
+            allocator.kill(ids[0]);
+
+            let json = serde_json::to_string(&allocator).unwrap();
+            let mut copy = serde_json::from_str::<Allocator<()>>(&json).unwrap();
+
+            for id in &ids {
+                assert_eq!(allocator.is_alive(*id), copy.is_alive(*id));
+            }
+
+            for _ in 0..5 {
+                assert_eq!(allocator.create_id(), copy.create_id());
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_keeps_reservations() {
+        for &reuse in &[SlotReuse::Lifo, SlotReuse::Fifo, SlotReuse::LowestIndex] {
+            let mut allocator = Allocator::<()>::default().with_slot_reuse(reuse);
+            let ids = (0..3).map(|_| allocator.create_id()).collect::<Vec<_>>();
+            allocator.kill(ids[1]);
+            let reserved = [allocator.reserve(), allocator.reserve()];
+
+            let json = serde_json::to_string(&allocator).unwrap();
+            let mut copy = serde_json::from_str::<Allocator<()>>(&json).unwrap();
+
+            let created = copy.create_id();
+            assert!(!reserved.contains(&created));
+            assert!(reserved.iter().all(|id| copy.is_alive(*id)));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_keeps_kill_log_capacity() {
+        let mut allocator = Allocator::<()>::default().with_kill_log(4);
+        let json = serde_json::to_string(&allocator).unwrap();
+        let mut copy = serde_json::from_str::<Allocator<()>>(&json).unwrap();
+
+        let gen = ArenaGen::default();
+        for allocator in [&mut allocator, &mut copy] {
+            let id = allocator.create_id();
+            allocator.kill(id);
+        }
+        assert_eq!(
+            allocator.killed_since(&gen).unwrap().iter().count(),
+            copy.killed_since(&gen).unwrap().iter().count()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_keeps_kill_log_batches() {
+        let mut allocator = Allocator::<()>::default().with_kill_log(4);
+        let ids = (0..3).map(|_| allocator.create_id()).collect::<Vec<_>>();
+        allocator.kill(ids[0]);
+        let _ = allocator.kill_multiple(&mut vec![ids[1], ids[2]]);
+
+        let json = serde_json::to_string(&allocator).unwrap();
+        let copy = serde_json::from_str::<Allocator<()>>(&json).unwrap();
+
+        let killed = copy.killed_since(&ArenaGen::default()).unwrap();
+        assert_eq!(ids, killed.iter().map(|id| *id.value).collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_detached_kill_log() {
+        let json = r#"{
+            "entries": [{"Dead": {"next_dead": null, "gen": 2}}],
+            "free": {"reuse": "Lifo", "head": 0, "tail": null, "lowest": []},
+            "gen": {"epoch": 0, "checksum": 0},
+            "exhaustion": "Retire",
+            "quarantine": {"delay": 0, "tick": 0, "slots": []},
+            "kill_log": {"capacity": 4, "batches": [[{"epoch": 0, "checksum": 0}, [{"index": 0, "gen": 1}]]]}
+        }"#;
+
+        assert_eq!(
+            Err(serde_impl::InvalidAllocator::DetachedKillLog),
+            try_from_json(json).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    fn try_from_json(json: &str) -> Result<UntypedAllocator, serde_impl::InvalidAllocator> {
+        let data = serde_json::from_str::<serde_impl::UntypedAllocatorData<Split32x32>>(json);
+        std::convert::TryFrom::try_from(data.unwrap())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_free_list_cycle() {
+        let json = r#"{
+            "entries": [
+                {"Dead": {"next_dead": 1, "gen": 2}},
+                {"Dead": {"next_dead": 0, "gen": 2}}
+            ],
+            "free": {"reuse": "Lifo", "head": 0, "tail": null, "lowest": []},
+            "gen": {"epoch": 0, "checksum": 0},
+            "exhaustion": "Retire",
+            "quarantine": {"delay": 0, "tick": 0, "slots": []}
+        }"#;
+
+        assert_eq!(
+            Err(serde_impl::InvalidAllocator::Repeated(0)),
+            try_from_json(json).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_free_list_out_of_bounds() {
+        let json = r#"{
+            "entries": [{"Dead": {"next_dead": 5, "gen": 2}}],
+            "free": {"reuse": "Lifo", "head": 0, "tail": null, "lowest": []},
+            "gen": {"epoch": 0, "checksum": 0},
+            "exhaustion": "Retire",
+            "quarantine": {"delay": 0, "tick": 0, "slots": []}
+        }"#;
+
+        assert_eq!(
+            Err(serde_impl::InvalidAllocator::OutOfBounds(5)),
+            try_from_json(json).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_free_list_links_to_living() {
+        let json = r#"{
+            "entries": [
+                {"Dead": {"next_dead": 1, "gen": 2}},
+                {"Alive": {"index": 1, "gen": 1}}
+            ],
+            "free": {"reuse": "Lifo", "head": 0, "tail": null, "lowest": []},
+            "gen": {"epoch": 0, "checksum": 0},
+            "exhaustion": "Retire",
+            "quarantine": {"delay": 0, "tick": 0, "slots": []}
+        }"#;
+
+        assert_eq!(
+            Err(serde_impl::InvalidAllocator::LinksToLiving(1)),
+            try_from_json(json).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_reservation_outside_free_list() {
+        let json = r#"{
+            "entries": [
+                {"Dead": {"next_dead": null, "gen": 2}},
+                {"Alive": {"index": 1, "gen": 1}}
+            ],
+            "free": {"reuse": "Lifo", "head": 0, "tail": null, "lowest": []},
+            "gen": {"epoch": 0, "checksum": 0},
+            "exhaustion": "Retire",
+            "quarantine": {"delay": 0, "tick": 0, "slots": []},
+            "reserved": {"next_dead": 2, "past_end": 0}
+        }"#;
+
+        assert_eq!(
+            Err(serde_impl::InvalidAllocator::ReservedOutsideFreeList(1)),
+            try_from_json(json).map(|_| ())
+        );
+    }
 }
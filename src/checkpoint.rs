@@ -0,0 +1,137 @@
+use crate::allocator::UntypedAllocator;
+use crate::layout::Split32x32;
+use force_derive::*;
+use std::marker::PhantomData;
+
+/// A snapshot of an allocator that can be restored later.
+///
+/// Entries are held in chunks that are shared with the allocator until either side writes to them.
+#[derive(Debug)]
+pub struct UntypedCheckpoint<L = Split32x32> {
+    /// Every field except the kill log
+    pub(crate) allocator: UntypedAllocator<L>,
+}
+
+impl<L> Clone for UntypedCheckpoint<L> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            allocator: self.allocator.clone(),
+        }
+    }
+}
+
+#[derive(Debug, ForceClone)]
+pub struct Checkpoint<Arena, L = Split32x32> {
+    untyped: UntypedCheckpoint<L>,
+    marker: PhantomData<fn() -> Arena>,
+}
+
+impl<Arena, L> Checkpoint<Arena, L> {
+    #[inline]
+    pub(crate) fn new(untyped: UntypedCheckpoint<L>) -> Self {
+        Self {
+            untyped,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn untyped(&self) -> &UntypedCheckpoint<L> {
+        &self.untyped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Allocator;
+
+    #[test]
+    fn restore_brings_back_free_list_gens_and_alloc_gen() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..600)
+            .map(|_| allocator.create().value)
+            .collect::<Vec<_>>();
+        allocator.kill(ids[10]);
+        allocator.kill(ids[20]);
+
+        let checkpoint = allocator.checkpoint();
+        let mut expected = allocator.clone();
+
+        allocator.kill(ids[30]);
+        let _ = allocator.create();
+        let _ = allocator.create();
+        allocator.restore(&checkpoint);
+
+        assert_eq!(expected.untyped.entries, allocator.untyped.entries);
+        assert_eq!(expected.untyped.gen, allocator.untyped.gen);
+        assert!(allocator.is_alive(ids[30]));
+
+        // the free list is restored, so the same Ids are created in the same order
+        for _ in 0..3 {
+            assert_eq!(expected.create().value, allocator.create().value);
+        }
+    }
+
+    #[test]
+    fn unchanged_chunks_are_shared() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..1000)
+            .map(|_| allocator.create().value)
+            .collect::<Vec<_>>();
+
+        let first = allocator.checkpoint();
+        allocator.kill(ids[999]);
+        let second = allocator.checkpoint();
+
+        let first = first.untyped().allocator.entries.chunks();
+        let second = second.untyped().allocator.entries.chunks();
+        assert_eq!(first.len(), second.len());
+        let shared = first
+            .iter()
+            .zip(second)
+            .filter(|(a, b)| std::sync::Arc::ptr_eq(a, b))
+            .count();
+        assert_eq!(first.len() - 1, shared);
+    }
+
+    #[test]
+    fn checkpoint_shares_entries_until_written() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..600)
+            .map(|_| allocator.create().value)
+            .collect::<Vec<_>>();
+
+        let checkpoint = allocator.checkpoint();
+        let shared = |allocator: &Allocator<()>, chunk: usize| {
+            let chunks = checkpoint.untyped().allocator.entries.chunks();
+            std::sync::Arc::ptr_eq(&chunks[chunk], &allocator.untyped.entries.chunks()[chunk])
+        };
+        assert!((0..3).all(|chunk| shared(&allocator, chunk)));
+
+        allocator.kill(ids[300]);
+        assert!(shared(&allocator, 0));
+        assert!(!shared(&allocator, 1));
+        assert!(shared(&allocator, 2));
+    }
+
+    #[test]
+    fn restore_drops_kills_logged_after_checkpoint() {
+        let mut allocator = Allocator::<()>::default().with_kill_log(4);
+        let ids = (0..4).map(|_| allocator.create().value).collect::<Vec<_>>();
+        let start = crate::ArenaGen::default();
+        allocator.kill(ids[0]);
+
+        let checkpoint = allocator.checkpoint();
+        let log = checkpoint.untyped().allocator.killed_since(start.untyped());
+        assert!(log.is_err());
+        allocator.kill(ids[1]);
+        allocator.restore(&checkpoint);
+
+        let killed = allocator.killed_since(&start).unwrap();
+        assert_eq!(
+            vec![ids[0]],
+            killed.iter().map(|id| *id.value).collect::<Vec<_>>()
+        );
+    }
+}
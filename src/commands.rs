@@ -0,0 +1,231 @@
+use crate::layout::{IdLayout, Split32x32};
+use crate::{Allocator, Id, Killed, Valid};
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+/// Records creates and kills from systems that only have shared access, to be applied at a sync point.
+///
+/// Creates are handed out immediately as reserved Ids. Kills are collected and applied together,
+/// sorted by Id so that the result does not depend on the order threads recorded them in.
+#[derive(Debug)]
+pub struct Commands<Arena, L: IdLayout = Split32x32> {
+    kills: Mutex<Vec<Id<Arena, L>>>,
+}
+
+impl<Arena, L: IdLayout> Default for Commands<Arena, L> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            kills: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<Arena, L: IdLayout> Commands<Arena, L> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The Id is alive once the commands are applied
+    #[inline]
+    pub fn create(&self, allocator: &Allocator<Arena, L>) -> Id<Arena, L> {
+        allocator.reserve()
+    }
+
+    #[inline]
+    pub fn kill(&self, id: Id<Arena, L>) {
+        self.kills.lock().unwrap().push(id);
+    }
+
+    #[inline]
+    pub fn kill_multiple<I: IntoIterator<Item = Id<Arena, L>>>(&self, ids: I) {
+        self.kills.lock().unwrap().extend(ids);
+    }
+
+    /// Makes created Ids alive, then kills every recorded Id in order.
+    /// Duplicate and dead Ids are filtered out of the returned batch.
+    #[must_use]
+    pub fn apply<'a>(&mut self, allocator: &'a mut Allocator<Arena, L>) -> Killed<'a, Arena, L> {
+        allocator.flush();
+
+        let kills = self.kills.get_mut().unwrap();
+        kills.sort_unstable();
+
+        allocator.kill_multiple(kills)
+    }
+}
+
+/// Creation requests that carry a caller-supplied key and are assigned Ids at a sync point.
+///
+/// Ids are handed out in key order rather than request order,
+/// so the Ids and allocator state are reproducible whatever the thread scheduling.
+#[derive(Debug)]
+pub struct KeyedCreates<Arena, K, L: IdLayout = Split32x32> {
+    keys: Mutex<Vec<K>>,
+    marker: PhantomData<fn() -> (Arena, L)>,
+}
+
+impl<Arena, K, L: IdLayout> Default for KeyedCreates<Arena, K, L> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            keys: Mutex::new(Vec::new()),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Arena, K: Ord, L: IdLayout> KeyedCreates<Arena, K, L> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn request(&self, key: K) {
+        self.keys.lock().unwrap().push(key);
+    }
+
+    /// Creates an Id for each request in key order.
+    ///
+    /// If two requests share a key their order would depend on the scheduling,
+    /// so every request is dropped and nothing is created.
+    pub fn commit<'a>(
+        &mut self,
+        allocator: &'a mut Allocator<Arena, L>,
+    ) -> Result<KeyedIds<'a, K, Arena, L>, DuplicateKey<K>> {
+        let mut keys = std::mem::take(self.keys.get_mut().unwrap());
+        keys.sort_unstable();
+        if let Some(i) = keys.windows(2).position(|pair| pair[0] == pair[1]) {
+            return Err(DuplicateKey(keys.swap_remove(i)));
+        }
+
+        Ok(keys
+            .into_iter()
+            .map(|key| (key, Valid::new(allocator.create_id())))
+            .collect())
+    }
+}
+
+/// The Ids created by a commit, paired with the keys that requested them
+type KeyedIds<'a, K, Arena, L> = Vec<(K, Valid<'a, Id<Arena, L>>)>;
+
+/// A key that was requested more than once before a commit
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DuplicateKey<K>(pub K);
+
+impl<K: Debug> Display for DuplicateKey<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "duplicate creation key {:?}", self.0)
+    }
+}
+
+impl<K: Debug> Error for DuplicateKey<K> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_is_independent_of_record_order() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..8).map(|_| allocator.create().value).collect::<Vec<_>>();
+        allocator.kill(ids[7]);
+
+        let mut commands = Commands::new();
+        std::thread::scope(|scope| {
+            for chunk in ids.chunks(2).rev() {
+                let commands = &commands;
+                scope.spawn(move || {
+                    commands.kill_multiple(chunk.iter().rev().copied());
+                    commands.kill(chunk[0]);
+                });
+            }
+        });
+
+        let killed = commands.apply(&mut allocator);
+        let killed = killed.iter().map(|id| *id.value).collect::<Vec<_>>();
+
+        assert_eq!(ids[..7], killed[..]);
+    }
+
+    #[test]
+    fn created_ids_are_alive_after_apply() {
+        let mut allocator = Allocator::<()>::default();
+        let mut commands = Commands::new();
+
+        let created = commands.create(&allocator);
+        let killed = commands.create(&allocator);
+        commands.kill(killed);
+        assert!(!allocator.is_alive(created));
+
+        let batch = commands.apply(&mut allocator);
+        assert_eq!(
+            vec![killed],
+            batch.iter().map(|id| *id.value).collect::<Vec<_>>()
+        );
+
+        assert!(allocator.is_alive(created));
+        assert!(!allocator.is_alive(killed));
+    }
+
+    fn keyed_creates(threads: usize) -> Vec<(usize, Id<()>)> {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..10)
+            .map(|_| allocator.create().value)
+            .collect::<Vec<_>>();
+        let mut ids = ids.into_iter().step_by(3).collect();
+        let _ = allocator.kill_multiple(&mut ids);
+
+        let mut creates = KeyedCreates::new();
+        std::thread::scope(|scope| {
+            for thread in 0..threads {
+                let creates = &creates;
+                scope.spawn(move || {
+                    for key in (thread..20).step_by(threads) {
+                        creates.request(key);
+                    }
+                });
+            }
+        });
+
+        let mut created = creates
+            .commit(&mut allocator)
+            .unwrap()
+            .into_iter()
+            .map(|(key, id)| (key, id.value))
+            .collect::<Vec<_>>();
+
+        // the allocator state after the commit shows in the Ids that follow it
+        created.extend((20..30).map(|key| (key, allocator.create().value)));
+        created
+    }
+
+    #[test]
+    fn keyed_creates_are_reproducible() {
+        let created = keyed_creates(1);
+
+        for threads in 2..6 {
+            assert_eq!(created, keyed_creates(threads));
+        }
+    }
+
+    #[test]
+    fn keyed_creates_rejects_duplicate_key() {
+        let mut allocator = Allocator::<()>::default();
+        let mut creates = KeyedCreates::new();
+        creates.request(2);
+        creates.request(1);
+        creates.request(2);
+
+        assert_eq!(
+            Err(DuplicateKey(2)),
+            creates.commit(&mut allocator).map(|_| ())
+        );
+        assert_eq!(0, allocator.ids().count());
+        assert!(creates.commit(&mut allocator).unwrap().is_empty());
+    }
+}
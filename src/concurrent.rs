@@ -0,0 +1,576 @@
+use crate::alloc_gen::{AllocGen, UntypedAllocGen};
+use crate::allocator::{Dead, Entry, FreeList, KillLog, Quarantine, SlotReuse, UntypedAllocator};
+use crate::entries::Entries;
+use crate::gen::{Gen, GenExhaustion};
+use crate::id::UntypedId;
+use crate::layout::{IdLayout, Split32x32};
+use crate::shared::AtomicSlots;
+use crate::{Allocator, Id, Killed};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const SHARDS: usize = 16;
+
+const ALIVE: u64 = 1 << 32;
+const RETIRED: u64 = 1 << 33;
+
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % SHARDS;
+}
+
+/// Each slot packs its generation into the low 32 bits, with flags for alive and retired above.
+/// A dead slot holds the generation its next Id will be given.
+#[inline]
+fn encode(entry: &Entry) -> u64 {
+    match entry {
+        Entry::Alive(id) => id.gen.get() as u64 | ALIVE,
+        Entry::Dead(dead) => dead.gen.get() as u64,
+        Entry::Retired => RETIRED,
+    }
+}
+
+#[inline]
+fn decode(index: usize, slot: u64) -> Entry {
+    let gen = Gen::new(slot as u32);
+    match gen {
+        Some(gen) if slot & ALIVE != 0 => Entry::Alive(UntypedId {
+            index: index as u32,
+            gen,
+        }),
+        Some(gen) => Entry::Dead(Dead {
+            next_dead: None,
+            gen,
+        }),
+        None => Entry::Retired,
+    }
+}
+
+/// The dead slots and kills of the threads that share a shard
+#[derive(Debug, Default)]
+struct Shard {
+    free: Vec<u32>,
+    /// Killed slots with the tick they were killed at, oldest first
+    quarantined: VecDeque<(u64, u32)>,
+    /// Kills that have not been taken with `killed`
+    killed: Vec<UntypedId>,
+}
+
+/// An allocator that can create and kill Ids through a shared reference.
+///
+/// Dead slots and kills are spread over per-thread shards so that threads rarely contend,
+/// and liveness is checked without a lock.
+/// The slot reuse policy is not followed while shared, and is restored on conversion back.
+#[derive(Debug)]
+pub struct UntypedConcurrentAllocator<L = Split32x32> {
+    slots: AtomicSlots,
+    len: AtomicUsize,
+    shards: Vec<Mutex<Shard>>,
+    /// The allocator generation when kills were last taken
+    synced: UntypedAllocGen,
+    retired: AtomicUsize,
+    reuse: SlotReuse,
+    exhaustion: GenExhaustion,
+    delay: u64,
+    tick: AtomicU64,
+    fresh_gen: Gen,
+    /// Each batch taken with `killed` is logged
+    kill_log: KillLog,
+    layout: PhantomData<L>,
+}
+
+impl<L: IdLayout> Default for UntypedConcurrentAllocator<L> {
+    #[inline]
+    fn default() -> Self {
+        Self::from(UntypedAllocator::default())
+    }
+}
+
+impl<L: IdLayout> UntypedConcurrentAllocator<L> {
+    /// Panics if the index space of the layout is exhausted
+    #[inline]
+    pub fn create(&self) -> UntypedId {
+        self.try_create().expect("Id index space exhausted")
+    }
+
+    /// Returns `None` if the index space of the layout is exhausted
+    pub fn try_create(&self) -> Option<UntypedId> {
+        if let Some(index) = self.pop_free() {
+            let slot = self.slots.get(index as usize)?;
+
+            // the index is owned by this thread until it is marked alive
+            let gen = Gen::new(slot.load(Ordering::Acquire) as u32)?;
+            slot.store(gen.get() as u64 | ALIVE, Ordering::Release);
+
+            return Some(UntypedId { index, gen });
+        }
+
+        let index = self
+            .len
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |len| {
+                (len <= L::MAX_INDEX as usize).then_some(len + 1)
+            })
+            .ok()?;
+
+        let id = UntypedId {
+            index: index as u32,
+            gen: self.fresh_gen,
+        };
+        let slot = self.slots.get_or_init(index);
+        slot.store(encode(&Entry::Alive(id)), Ordering::Release);
+        Some(id)
+    }
+
+    fn pop_free(&self) -> Option<u32> {
+        let own = SHARD.with(|shard| *shard);
+        (0..SHARDS)
+            .map(|offset| (own + offset) % SHARDS)
+            .find_map(|shard| self.shards[shard].lock().unwrap().free.pop())
+    }
+
+    /// Returns false if the Id was already dead, including when another thread killed it first
+    pub fn kill(&self, id: UntypedId) -> bool {
+        let next = self.exhaustion.next_gen(id, L::MAX_GEN);
+        let dead = match next {
+            Some(gen) => gen.get() as u64,
+            None => RETIRED,
+        };
+
+        let alive = encode(&Entry::Alive(id));
+        let killed = self
+            .slots
+            .get(id.index())
+            .map(|slot| slot.compare_exchange(alive, dead, Ordering::AcqRel, Ordering::Acquire));
+        if !matches!(killed, Some(Ok(_))) {
+            return false;
+        }
+
+        let mut shard = self.shards[SHARD.with(|shard| *shard)].lock().unwrap();
+        match next {
+            Some(_) if self.delay == 0 => shard.free.push(id.index),
+            Some(_) => {
+                // loaded under the lock so that each shard stays in order of tick
+                let tick = self.tick.load(Ordering::Acquire);
+                shard.quarantined.push_back((tick, id.index));
+            }
+            None => {
+                self.retired.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        shard.killed.push(id);
+
+        true
+    }
+
+    /// Releases any quarantined slots whose delay has passed
+    pub fn advance_tick(&self) {
+        let tick = self.tick.fetch_add(1, Ordering::AcqRel) + 1;
+        for shard in &self.shards {
+            let mut shard = shard.lock().unwrap();
+            while let Some(&(killed, index)) = shard.quarantined.front() {
+                if killed + self.delay > tick {
+                    break;
+                }
+                shard.quarantined.pop_front();
+                shard.free.push(index);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn tick(&self) -> u64 {
+        self.tick.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub fn is_alive(&self, id: UntypedId) -> bool {
+        let alive = encode(&Entry::Alive(id));
+        matches!(self.slots.get(id.index()), Some(slot) if slot.load(Ordering::Acquire) == alive)
+    }
+
+    /// Takes the Ids killed since the last call from every shard, and logs them as a single batch
+    pub fn killed(&mut self) -> Vec<UntypedId> {
+        let ids = self
+            .shards
+            .iter_mut()
+            .flat_map(|shard| std::mem::take(&mut shard.get_mut().unwrap().killed))
+            .collect::<Vec<_>>();
+
+        let before = self.synced.clone();
+        for id in &ids {
+            self.synced.increment_gen(*id);
+        }
+        self.kill_log.push(before, &ids);
+        ids
+    }
+}
+
+impl<L: IdLayout> From<UntypedAllocator<L>> for UntypedConcurrentAllocator<L> {
+    fn from(mut allocator: UntypedAllocator<L>) -> Self {
+        allocator.flush();
+        let UntypedAllocator {
+            entries,
+            mut free,
+            gen,
+            exhaustion,
+            quarantine,
+            fresh_gen,
+            retired,
+            kill_log,
+            ..
+        } = allocator;
+
+        let mut shards = (0..SHARDS).map(|_| Shard::default()).collect::<Vec<_>>();
+        let mut next = Vec::new();
+        while let Some(index) = free.pop(&entries) {
+            next.push(index);
+        }
+        // the next slot to be reused ends up on top of a shard
+        for (i, index) in next.into_iter().rev().enumerate() {
+            shards[i % SHARDS].free.push(index);
+        }
+        for (i, slot) in quarantine.slots.into_iter().enumerate() {
+            shards[i % SHARDS].quarantined.push_back(slot);
+        }
+
+        let slots = AtomicSlots::default();
+        for (index, entry) in entries.iter().enumerate() {
+            slots
+                .get_or_init(index)
+                .store(encode(entry), Ordering::Relaxed);
+        }
+
+        Self {
+            slots,
+            len: AtomicUsize::new(entries.len()),
+            shards: shards.into_iter().map(Mutex::new).collect(),
+            synced: gen,
+            retired: AtomicUsize::new(retired),
+            reuse: free.reuse,
+            exhaustion,
+            delay: quarantine.delay,
+            tick: AtomicU64::new(quarantine.tick),
+            fresh_gen,
+            kill_log,
+            layout: PhantomData,
+        }
+    }
+}
+
+/// Kills that have not been taken with `killed` are not reported by the allocator, but are logged
+impl<L: IdLayout> From<UntypedConcurrentAllocator<L>> for UntypedAllocator<L> {
+    fn from(mut allocator: UntypedConcurrentAllocator<L>) -> Self {
+        let _ = allocator.killed();
+        let UntypedConcurrentAllocator {
+            slots,
+            len,
+            shards,
+            synced,
+            retired,
+            reuse,
+            exhaustion,
+            delay,
+            tick,
+            fresh_gen,
+            kill_log,
+            ..
+        } = allocator;
+
+        let mut entries: Entries = (0..len.into_inner())
+            .map(|index| match slots.get(index) {
+                Some(slot) => decode(index, slot.load(Ordering::Acquire)),
+                None => Entry::Retired,
+            })
+            .collect::<Vec<_>>()
+            .into();
+
+        let mut free = FreeList::new(reuse);
+        let mut quarantined = Vec::new();
+        for shard in shards {
+            let shard = shard.into_inner().unwrap();
+            for index in shard.free {
+                if let Some(&Dead { gen, .. }) = entries[index as usize].dead() {
+                    free.push(&mut entries, index, gen);
+                }
+            }
+            quarantined.extend(shard.quarantined);
+        }
+        quarantined.sort_by_key(|&(tick, _)| tick);
+
+        let quarantine = Quarantine {
+            delay,
+            tick: tick.into_inner(),
+            slots: quarantined.into(),
+        };
+
+        UntypedAllocator {
+            entries,
+            free,
+            gen: synced,
+            exhaustion,
+            quarantine,
+            fresh_gen,
+            retired: retired.into_inner(),
+            reserved: Default::default(),
+            kill_log,
+            layout: PhantomData,
+        }
+    }
+}
+
+/// An allocator that can create and kill Ids from many threads through a shared reference.
+///
+/// Ids are not handed out as `Valid`, as another thread may kill them at any time.
+/// Kills are collected into a `Killed` batch at a sync point with `killed`.
+#[derive(Debug)]
+pub struct ConcurrentAllocator<Arena, L = Split32x32> {
+    untyped: UntypedConcurrentAllocator<L>,
+    arena: PhantomData<fn() -> Arena>,
+}
+
+impl<Arena, L: IdLayout> Default for ConcurrentAllocator<Arena, L> {
+    #[inline]
+    fn default() -> Self {
+        Self::from(Allocator::default())
+    }
+}
+
+impl<Arena, L: IdLayout> ConcurrentAllocator<Arena, L> {
+    /// Panics if the index space of the layout is exhausted
+    #[inline]
+    pub fn create(&self) -> Id<Arena, L> {
+        Id::new(self.untyped.create())
+    }
+
+    /// Returns `None` if the index space of the layout is exhausted
+    #[inline]
+    pub fn try_create(&self) -> Option<Id<Arena, L>> {
+        self.untyped.try_create().map(Id::new)
+    }
+
+    #[inline]
+    pub fn kill(&self, id: Id<Arena, L>) -> bool {
+        self.untyped.kill(id.untyped())
+    }
+
+    /// Releases any quarantined slots whose delay has passed
+    #[inline]
+    pub fn advance_tick(&self) {
+        self.untyped.advance_tick();
+    }
+
+    #[inline]
+    pub fn tick(&self) -> u64 {
+        self.untyped.tick()
+    }
+
+    #[inline]
+    pub fn is_alive(&self, id: Id<Arena, L>) -> bool {
+        self.untyped.is_alive(id.untyped())
+    }
+
+    /// Takes the Ids killed since the last call, for the purpose of notifying other arenas of their deletion
+    #[must_use]
+    pub fn killed(&mut self) -> Killed<'_, Arena, L> {
+        let before = AllocGen::new(self.untyped.synced.clone());
+        let ids = self.untyped.killed().into_iter().map(Id::new).collect();
+        let after = AllocGen::new(self.untyped.synced.clone());
+
+        Killed::new(ids, before, after, &self.untyped.kill_log)
+    }
+}
+
+impl<Arena, L: IdLayout> From<Allocator<Arena, L>> for ConcurrentAllocator<Arena, L> {
+    #[inline]
+    fn from(allocator: Allocator<Arena, L>) -> Self {
+        Self {
+            untyped: allocator.untyped.into(),
+            arena: PhantomData,
+        }
+    }
+}
+
+/// Kills that have not been taken with `killed` are not reported by the allocator
+impl<Arena, L: IdLayout> From<ConcurrentAllocator<Arena, L>> for Allocator<Arena, L> {
+    #[inline]
+    fn from(allocator: ConcurrentAllocator<Arena, L>) -> Self {
+        Self {
+            untyped: allocator.untyped.into(),
+            arena: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ArenaGen;
+    use std::collections::HashSet;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn create_and_kill_from_many_threads() {
+        let mut allocator = ConcurrentAllocator::<()>::default();
+
+        let ids = std::thread::scope(|scope| {
+            let workers = (0..4)
+                .map(|_| {
+                    let allocator = &allocator;
+                    scope.spawn(move || {
+                        let mut ids = (0..100).map(|_| allocator.create()).collect::<Vec<_>>();
+                        for id in ids.drain(..50) {
+                            assert!(allocator.kill(id));
+                            assert!(!allocator.kill(id));
+                        }
+                        ids.extend((0..50).map(|_| allocator.create()));
+                        ids
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let unique = ids.iter().map(|id| id.index()).collect::<HashSet<_>>();
+        assert_eq!(400, unique.len());
+        assert!(ids.iter().all(|id| allocator.is_alive(*id)));
+
+        let killed = allocator.killed();
+        assert_eq!(200, killed.iter().count());
+    }
+
+    #[test]
+    fn killed_updates_arena_gen() {
+        let mut allocator = ConcurrentAllocator::<()>::default();
+        let mut gen = ArenaGen::default();
+
+        let id = allocator.create();
+        allocator.kill(id);
+        allocator.killed().update_gen(&mut gen);
+
+        allocator.kill(allocator.create());
+        let killed = allocator.killed();
+        killed.update_gen(&mut gen);
+        assert!(gen == *killed.after());
+    }
+
+    #[test]
+    fn round_trip_through_allocator() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..3).map(|_| allocator.create().value).collect::<Vec<_>>();
+        allocator.kill(ids[1]);
+
+        let concurrent = ConcurrentAllocator::from(allocator);
+        assert!(concurrent.is_alive(ids[0]));
+        assert!(!concurrent.is_alive(ids[1]));
+
+        let reused = concurrent.create();
+        assert_eq!(1, reused.index());
+        concurrent.kill(ids[2]);
+
+        let mut allocator = Allocator::from(concurrent);
+        assert!(allocator.is_alive(reused));
+        assert!(!allocator.is_alive(ids[2]));
+
+        let id = allocator.create().value;
+        assert_eq!(2, id.index());
+        assert_eq!(2, id.gen().get());
+    }
+
+    #[test]
+    fn round_trip_keeps_kill_log() {
+        let mut allocator = Allocator::<()>::default().with_kill_log(4);
+        let ids = (0..3).map(|_| allocator.create().value).collect::<Vec<_>>();
+        let gen = ArenaGen::default();
+
+        let mut concurrent = ConcurrentAllocator::from(allocator);
+        concurrent.kill(ids[0]);
+        let _ = concurrent.killed();
+        concurrent.kill(ids[1]);
+
+        let allocator = Allocator::from(concurrent);
+        let killed = allocator.killed_since(&gen).unwrap();
+        assert_eq!(
+            ids[..2],
+            killed.iter().map(|id| *id.value).collect::<Vec<_>>()[..]
+        );
+    }
+
+    #[test]
+    fn is_alive_while_growing() {
+        let allocator = ConcurrentAllocator::<()>::default();
+        let kept = allocator.create();
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            let (allocator, done) = (&allocator, &done);
+            scope.spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    assert!(allocator.is_alive(kept));
+                }
+            });
+
+            let ids = std::thread::scope(|inner| {
+                let workers = (0..4)
+                    .map(|_| {
+                        inner.spawn(move || {
+                            (0..1000).map(|_| allocator.create()).collect::<Vec<_>>()
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                workers
+                    .into_iter()
+                    .flat_map(|worker| worker.join().unwrap())
+                    .collect::<Vec<_>>()
+            });
+            done.store(true, Ordering::Relaxed);
+
+            assert!(ids.iter().all(|id| allocator.is_alive(*id)));
+        });
+    }
+
+    #[test]
+    fn advance_tick_releases_quarantined_slots() {
+        let mut allocator = Allocator::<()>::default().with_quarantine(2);
+        let before = allocator.create().value;
+        allocator.kill(before);
+
+        let concurrent = ConcurrentAllocator::from(allocator);
+        let id = concurrent.create();
+        concurrent.advance_tick();
+        concurrent.kill(id);
+        assert_eq!(2, concurrent.create().index());
+
+        concurrent.advance_tick();
+        assert_eq!(0, concurrent.create().index());
+        assert_eq!(3, concurrent.create().index());
+        concurrent.advance_tick();
+        assert_eq!(1, concurrent.create().index());
+
+        let allocator = Allocator::from(concurrent);
+        assert_eq!((3, 0), (allocator.tick(), allocator.quarantined()));
+    }
+
+    #[test]
+    fn quarantine_survives_round_trip() {
+        let mut allocator = Allocator::<()>::default().with_quarantine(2);
+        let ids = (0..2).map(|_| allocator.create().value).collect::<Vec<_>>();
+        allocator.kill(ids[0]);
+        allocator.advance_tick();
+
+        let concurrent = ConcurrentAllocator::from(allocator);
+        concurrent.kill(ids[1]);
+
+        let mut allocator = Allocator::from(concurrent);
+        assert_eq!(2, allocator.quarantined());
+        allocator.advance_tick();
+        assert_eq!(1, allocator.quarantined());
+        assert_eq!(0, allocator.create().value.index());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::allocator::Entry;
+use std::fmt::{Debug, Formatter};
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+/// The number of entries in each chunk
+pub(crate) const CHUNK: usize = 256;
+
+/// Allocator entries held in fixed-size chunks.
+/// Clones share their chunks, and a shared chunk is copied on its first write.
+#[derive(Default, Clone)]
+pub(crate) struct Entries {
+    chunks: Vec<Arc<[Entry]>>,
+    len: usize,
+}
+
+impl Entries {
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, index: usize) -> Option<&Entry> {
+        if index < self.len {
+            Some(&self.chunks[index / CHUNK][index % CHUNK])
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut Entry> {
+        if index < self.len {
+            let chunk = Arc::make_mut(&mut self.chunks[index / CHUNK]);
+            Some(&mut chunk[index % CHUNK])
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn last(&self) -> Option<&Entry> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    #[inline]
+    pub(crate) fn push(&mut self, entry: Entry) {
+        if self.len == self.chunks.len() * CHUNK {
+            self.chunks.push(vec![Entry::Retired; CHUNK].into());
+        }
+        let index = self.len;
+        self.len += 1;
+        self[index] = entry;
+    }
+
+    /// Entries past the end are left in place until they are overwritten by `push`
+    #[inline]
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.len = len;
+            self.chunks.truncate(len.div_ceil(CHUNK));
+        }
+    }
+
+    #[inline]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Entry> + '_ {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.iter())
+            .take(self.len)
+    }
+
+    /// The chunks that hold the entries, the last of which may extend past the end
+    #[inline]
+    pub(crate) fn chunks(&self) -> &[Arc<[Entry]>] {
+        &self.chunks
+    }
+}
+
+impl Index<usize> for Entries {
+    type Output = Entry;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Entry {
+        match self.get(index) {
+            Some(entry) => entry,
+            None => panic!("entry {} is out of bounds at length {}", index, self.len),
+        }
+    }
+}
+
+impl IndexMut<usize> for Entries {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Entry {
+        let len = self.len;
+        match self.get_mut(index) {
+            Some(entry) => entry,
+            None => panic!("entry {} is out of bounds at length {}", index, len),
+        }
+    }
+}
+
+impl From<Vec<Entry>> for Entries {
+    fn from(entries: Vec<Entry>) -> Self {
+        let mut result = Self::default();
+        for entry in entries {
+            result.push(entry);
+        }
+        result
+    }
+}
+
+impl PartialEq for Entries {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Debug for Entries {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Entries {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id::UntypedId;
+
+    fn alive(index: u32) -> Entry {
+        Entry::Alive(UntypedId {
+            index,
+            gen: Default::default(),
+        })
+    }
+
+    #[test]
+    fn clones_share_chunks_until_written() {
+        let mut entries = Entries::from((0..600).map(alive).collect::<Vec<_>>());
+        let copy = entries.clone();
+
+        entries[300] = Entry::Retired;
+
+        let shared = |i: usize| Arc::ptr_eq(&entries.chunks()[i], &copy.chunks()[i]);
+        assert!(shared(0));
+        assert!(!shared(1));
+        assert!(shared(2));
+        assert_eq!(alive(300), copy[300]);
+    }
+
+    #[test]
+    fn truncate_then_push_overwrites_stale_entries() {
+        let mut entries = Entries::from((0..300).map(alive).collect::<Vec<_>>());
+        entries.truncate(2);
+        assert_eq!(1, entries.chunks().len());
+        assert_eq!(None, entries.get(2));
+
+        entries.push(Entry::Retired);
+        assert_eq!(3, entries.len());
+        assert_eq!(Some(&Entry::Retired), entries.last());
+        assert_eq!(3, entries.iter().count());
+    }
+}
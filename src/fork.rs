@@ -0,0 +1,178 @@
+use crate::checkpoint::UntypedCheckpoint;
+use crate::layout::{IdLayout, Split32x32};
+use crate::Allocator;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+/// A child allocator created by [`Allocator::fork`].
+///
+/// The child and the state at the fork share their entry chunks with the parent,
+/// and a chunk is only copied when one of them writes to it. Dropping the fork discards it.
+#[derive(Debug)]
+pub struct Fork<Arena, L: IdLayout = Split32x32> {
+    allocator: Allocator<Arena, L>,
+    base: UntypedCheckpoint<L>,
+}
+
+impl<Arena, L: IdLayout> Fork<Arena, L> {
+    pub(crate) fn new(parent: &mut Allocator<Arena, L>) -> Self {
+        Self {
+            base: parent.untyped.checkpoint(),
+            allocator: parent.clone(),
+        }
+    }
+
+    #[inline]
+    pub fn allocator(&self) -> &Allocator<Arena, L> {
+        &self.allocator
+    }
+
+    #[inline]
+    pub fn allocator_mut(&mut self) -> &mut Allocator<Arena, L> {
+        &mut self.allocator
+    }
+
+    /// The parent as it was at the fork
+    #[inline]
+    pub(crate) fn base(&self) -> &UntypedCheckpoint<L> {
+        &self.base
+    }
+
+    #[inline]
+    pub(crate) fn into_parts(self) -> (Allocator<Arena, L>, UntypedCheckpoint<L>) {
+        (self.allocator, self.base)
+    }
+}
+
+/// The parent changed after the fork, so the child cannot be committed
+pub struct ForkConflict<Arena, L: IdLayout = Split32x32> {
+    /// The indices the parent changed since the fork, in order
+    pub indices: Vec<u32>,
+    fork: Box<Fork<Arena, L>>,
+}
+
+impl<Arena, L: IdLayout> ForkConflict<Arena, L> {
+    #[inline]
+    pub(crate) fn new(indices: Vec<u32>, fork: Fork<Arena, L>) -> Self {
+        Self {
+            indices,
+            fork: Box::new(fork),
+        }
+    }
+
+    /// The rejected child, unchanged
+    #[inline]
+    pub fn into_fork(self) -> Fork<Arena, L> {
+        *self.fork
+    }
+}
+
+impl<Arena, L: IdLayout> Debug for ForkConflict<Arena, L> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ForkConflict")
+            .field("indices", &self.indices)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Arena, L: IdLayout> Display for ForkConflict<Arena, L> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "allocator changed {} indices since the fork",
+            self.indices.len()
+        )
+    }
+}
+
+impl<Arena, L: IdLayout> Error for ForkConflict<Arena, L> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commit_applies_child() {
+        let mut parent = Allocator::<()>::default();
+        let ids = (0..300).map(|_| parent.create().value).collect::<Vec<_>>();
+
+        let mut child = parent.fork();
+        child.allocator_mut().kill(ids[0]);
+        let created = child.allocator_mut().create().value;
+        let expected = child.allocator().clone();
+
+        parent.commit(child).unwrap();
+
+        assert!(!parent.is_alive(ids[0]));
+        assert!(parent.is_alive(created));
+        assert_eq!(expected.untyped.entries, parent.untyped.entries);
+        assert_eq!(expected.untyped.gen, parent.untyped.gen);
+    }
+
+    #[test]
+    fn fork_shares_chunks_until_written() {
+        let mut parent = Allocator::<()>::default();
+        let ids = (0..600).map(|_| parent.create().value).collect::<Vec<_>>();
+
+        let mut child = parent.fork();
+        child.allocator_mut().kill(ids[300]);
+
+        let chunks = parent.untyped.entries.chunks();
+        let child_chunks = child.allocator().untyped.entries.chunks();
+        let shared = |chunk: usize| std::sync::Arc::ptr_eq(&chunks[chunk], &child_chunks[chunk]);
+        assert!(shared(0));
+        assert!(!shared(1));
+        assert!(shared(2));
+    }
+
+    #[test]
+    fn commit_conflicts_on_disjoint_parent_change() {
+        let mut parent = Allocator::<()>::default();
+        let ids = (0..300).map(|_| parent.create().value).collect::<Vec<_>>();
+
+        let mut child = parent.fork();
+        child.allocator_mut().kill(ids[5]);
+
+        parent.kill(ids[280]);
+        let conflict = parent.commit(child).unwrap_err();
+        assert_eq!(vec![280], conflict.indices);
+        assert!(parent.is_alive(ids[5]));
+        assert!(!parent.is_alive(ids[280]));
+    }
+
+    #[test]
+    fn commit_conflicts_on_parent_tick() {
+        let mut parent = Allocator::<()>::default();
+        let id = parent.create().value;
+
+        let mut child = parent.fork();
+        child.allocator_mut().kill(id);
+
+        parent.advance_tick();
+        let conflict = parent.commit(child).unwrap_err();
+        assert!(conflict.indices.is_empty());
+        assert!(parent.is_alive(id));
+    }
+
+    #[test]
+    fn commit_conflict_lists_contested_indices() {
+        let mut parent = Allocator::<()>::default();
+        let ids = (0..300).map(|_| parent.create().value).collect::<Vec<_>>();
+
+        let mut child = parent.fork();
+        child.allocator_mut().kill(ids[5]);
+
+        parent.kill(ids[5]);
+        parent.kill(ids[280]);
+        let created = (0..3).map(|_| parent.create().value).collect::<Vec<_>>();
+
+        let conflict = parent.commit(child).unwrap_err();
+        assert_eq!(vec![5, 280, 300], conflict.indices);
+        assert!(created.iter().all(|id| parent.is_alive(*id)));
+
+        // the child is handed back unchanged
+        let child = conflict.into_fork();
+        assert!(!child.allocator().is_alive(ids[5]));
+        assert!(child.allocator().is_alive(ids[280]));
+    }
+}
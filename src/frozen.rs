@@ -0,0 +1,116 @@
+use crate::alloc_gen::AllocGen;
+use crate::layout::{IdLayout, Split32x32};
+use crate::{Allocator, Id, Valid, Validator};
+use force_derive::*;
+use std::sync::Arc;
+
+/// A read-only allocator that can be shared across threads, created by [`Allocator::freeze`].
+///
+/// Validation reads the allocator entries in place, so freezing copies nothing.
+#[derive(Debug, ForceClone)]
+pub struct FrozenAllocator<Arena, L = Split32x32>(Arc<Allocator<Arena, L>>);
+
+impl<Arena, L: IdLayout> From<Allocator<Arena, L>> for FrozenAllocator<Arena, L> {
+    #[inline]
+    fn from(mut allocator: Allocator<Arena, L>) -> Self {
+        allocator.flush();
+        Self(Arc::new(allocator))
+    }
+}
+
+impl<Arena, L: IdLayout> FrozenAllocator<Arena, L> {
+    #[inline]
+    pub fn is_alive(&self, id: Id<Arena, L>) -> bool {
+        self.0.is_alive(id)
+    }
+
+    #[inline]
+    pub fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'_, Id<Arena, L>>> {
+        Validator::validate(&self, id)
+    }
+
+    #[inline]
+    pub fn ids<'valid>(&'valid self) -> impl Iterator<Item = Valid<'valid, Id<Arena, L>>> + 'valid {
+        self.0.ids()
+    }
+
+    /// Returns the allocator without copying it if this is the last handle, otherwise gives the handle back
+    #[inline]
+    pub fn thaw(self) -> Result<Allocator<Arena, L>, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+
+impl<Arena, L> AsRef<AllocGen<Arena>> for FrozenAllocator<Arena, L> {
+    #[inline]
+    fn as_ref(&self) -> &AllocGen<Arena> {
+        AsRef::as_ref(&*self.0)
+    }
+}
+
+impl<'valid, Arena, L: IdLayout> Validator<'valid, Arena, L> for &'valid FrozenAllocator<Arena, L> {
+    #[inline]
+    fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'valid, Id<Arena, L>>> {
+        self.is_alive(id).then(|| Valid::new(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frozen_matches_allocator() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..130)
+            .map(|_| allocator.create().value)
+            .collect::<Vec<_>>();
+        allocator.kill(ids[3]);
+        allocator.kill(ids[100]);
+        let stale = ids[3];
+        let reserved = allocator.reserve();
+
+        let mut expected = allocator
+            .ids()
+            .map(|id| id.value)
+            .chain(Some(reserved))
+            .collect::<Vec<_>>();
+        let frozen = allocator.freeze();
+
+        assert!(!frozen.is_alive(stale));
+        assert!(frozen.is_alive(reserved));
+        let mut frozen_ids = frozen.ids().map(|id| id.value).collect::<Vec<_>>();
+        frozen_ids.sort();
+        expected.sort();
+        assert_eq!(expected, frozen_ids);
+    }
+
+    #[test]
+    fn validate_across_threads_then_thaw() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..100)
+            .map(|_| allocator.create().value)
+            .collect::<Vec<_>>();
+        allocator.kill(ids[50]);
+        let entries = allocator.untyped.entries.chunks().as_ptr();
+
+        let frozen = allocator.freeze();
+        let handles = (0..4)
+            .map(|_| {
+                let frozen = frozen.clone();
+                let ids = ids.clone();
+                std::thread::spawn(move || ids.iter().filter_map(|id| frozen.validate(*id)).count())
+            })
+            .collect::<Vec<_>>();
+
+        let reader = frozen.clone();
+        let frozen = frozen.thaw().unwrap_err();
+        for handle in handles {
+            assert_eq!(99, handle.join().unwrap());
+        }
+        drop(reader);
+
+        let allocator = frozen.thaw().unwrap();
+        assert_eq!(entries, allocator.untyped.entries.chunks().as_ptr());
+    }
+}
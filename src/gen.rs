@@ -1,6 +1,11 @@
 use std::num::NonZeroU32;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub(crate) struct Gen(NonZeroU32);
 
 impl Default for Gen {
@@ -19,7 +24,41 @@ impl Gen {
     }
 
     pub fn next(&self) -> Self {
-        let gen = self.0.get().wrapping_add(1);
-        Self::new(gen).unwrap_or_default()
+        self.checked_next(u32::MAX).unwrap_or_default()
+    }
+
+    /// Returns `None` if the next generation would exceed `max`
+    pub fn checked_next(&self, max: u32) -> Option<Self> {
+        let gen = self.get();
+        if gen < max {
+            Self::new(gen + 1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Determines what an allocator does with a slot once its generation can no longer be incremented
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GenExhaustion {
+    /// The slot is never reused, so no Id can be handed out twice
+    Retire,
+    /// The generation restarts at 1, which can bring old Ids back to life.
+    /// This is the default, as retiring slots gives up capacity.
+    #[default]
+    Wrap,
+    Panic,
+}
+
+impl GenExhaustion {
+    /// The generation a slot moves to when `id` is killed, or `None` if the slot is retired
+    pub(crate) fn next_gen(self, id: crate::id::UntypedId, max: u32) -> Option<Gen> {
+        match (id.gen.checked_next(max), self) {
+            (Some(gen), _) => Some(gen),
+            (None, GenExhaustion::Wrap) => Some(Gen::default()),
+            (None, GenExhaustion::Retire) => None,
+            (None, GenExhaustion::Panic) => panic!("generation exhausted for index {}", id.index),
+        }
     }
 }
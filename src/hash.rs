@@ -0,0 +1,137 @@
+use crate::layout::{IdLayout, Split32x32};
+use crate::{Allocator, Id};
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+
+const SEED: u64 = 0xf135_7aea_2e62_a9c5;
+
+/// A multiply-rotate hasher for Ids, which hash as a single `u64` of index and generation.
+///
+/// It is not randomly seeded, so a map that sees the same operations iterates in the same order.
+/// Not resistant to adversarial keys.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct IdHasher(u64);
+
+impl Hasher for IdHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        // the multiply leaves the most entropy in the high bits, but tables index with the low bits
+        self.0.rotate_left(26)
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0u8; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
+        }
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0.rotate_left(5) ^ i).wrapping_mul(SEED);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+pub type IdBuildHasher = BuildHasherDefault<IdHasher>;
+
+pub type IdHashMap<Arena, V, L = Split32x32> = HashMap<Id<Arena, L>, V, IdBuildHasher>;
+
+pub type IdHashSet<Arena, L = Split32x32> = HashSet<Id<Arena, L>, IdBuildHasher>;
+
+/// Removes any entries keyed by Ids that are no longer alive
+pub trait RetainAlive<Arena, L: IdLayout = Split32x32> {
+    fn retain_alive(&mut self, allocator: &Allocator<Arena, L>);
+}
+
+impl<Arena, V, L: IdLayout, S: BuildHasher> RetainAlive<Arena, L> for HashMap<Id<Arena, L>, V, S> {
+    #[inline]
+    fn retain_alive(&mut self, allocator: &Allocator<Arena, L>) {
+        self.retain(|id, _| allocator.is_alive(*id));
+    }
+}
+
+impl<Arena, L: IdLayout, S: BuildHasher> RetainAlive<Arena, L> for HashSet<Id<Arena, L>, S> {
+    #[inline]
+    fn retain_alive(&mut self, allocator: &Allocator<Arena, L>) {
+        self.retain(|id| allocator.is_alive(*id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id::UntypedId;
+    use std::hash::Hash;
+
+    #[test]
+    fn id_hashes_as_single_word() {
+        #[derive(Default)]
+        struct Words(Vec<u64>);
+
+        impl Hasher for Words {
+            fn finish(&self) -> u64 {
+                0
+            }
+
+            fn write(&mut self, _: &[u8]) {
+                panic!("expected a single u64 write");
+            }
+
+            fn write_u64(&mut self, i: u64) {
+                self.0.push(i);
+            }
+        }
+
+        let mut words = Words::default();
+        Id::<()>::first(3).hash(&mut words);
+
+        assert_eq!(vec![UntypedId::first(3).bits()], words.0);
+    }
+
+    #[test]
+    fn iteration_order_is_deterministic() {
+        let fill = || {
+            (0..100)
+                .map(|i| (Id::<()>::first(i), i))
+                .collect::<IdHashMap<(), usize>>()
+        };
+
+        let a = fill().into_iter().collect::<Vec<_>>();
+        let b = fill().into_iter().collect::<Vec<_>>();
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn retain_alive() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..3).map(|_| allocator.create().value).collect::<Vec<_>>();
+        allocator.kill(ids[1]);
+
+        let mut map = ids
+            .iter()
+            .map(|id| (*id, ()))
+            .collect::<IdHashMap<(), ()>>();
+        let mut set = ids.iter().copied().collect::<IdHashSet<()>>();
+
+        map.retain_alive(&allocator);
+        set.retain_alive(&allocator);
+
+        assert!(!map.contains_key(&ids[1]));
+        assert!(!set.contains(&ids[1]));
+        assert_eq!(2, map.len());
+        assert_eq!(2, set.len());
+    }
+}
@@ -1,12 +1,14 @@
 use crate::gen::Gen;
+use crate::layout::{IdLayout, Split32x32};
 use crate::{Fixed, ValidId};
-use force_derive::*;
 use ref_cast::RefCast;
 use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 
-#[allow(clippy::derive_hash_xor_eq)]
-#[derive(Debug, Copy, Clone, Ord, PartialOrd, Hash)]
+#[derive(Debug, Copy, Clone, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UntypedId {
     pub index: u32,
     pub(crate) gen: Gen,
@@ -20,17 +22,25 @@ impl PartialEq for UntypedId {
 
 impl Eq for UntypedId {}
 
+/// Written as a single `u64` so that `IdHasher` only has to mix one word
+impl Hash for UntypedId {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.bits());
+    }
+}
+
 impl UntypedId {
     #[cfg(feature = "id_creation")]
     #[inline]
     pub fn first(index: usize) -> Self {
-        UntypedId::first_u32(index as u32)
+        UntypedId::first_u32(u32::try_from(index).expect("Id index out of range"))
     }
 
     #[cfg(not(feature = "id_creation"))]
     #[inline]
     pub(crate) fn first(index: usize) -> Self {
-        UntypedId::first_u32(index as u32)
+        UntypedId::first_u32(u32::try_from(index).expect("Id index out of range"))
     }
 
     #[inline]
@@ -58,27 +68,52 @@ impl UntypedId {
 }
 
 #[repr(transparent)]
-#[derive(Debug, ForceCopy, ForceClone, ForceEq, ForcePartialEq, ForceHash, RefCast)]
-pub struct Id<Arena> {
-    pub untyped: UntypedId,
-    marker: PhantomData<*const Arena>,
+#[derive(Debug, RefCast)]
+pub struct Id<Arena, L: IdLayout = Split32x32> {
+    raw: L::Raw,
+    marker: PhantomData<fn() -> Arena>,
+}
+
+impl<Arena, L: IdLayout> Copy for Id<Arena, L> {}
+
+impl<Arena, L: IdLayout> Clone for Id<Arena, L> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
-impl<Arena> Ord for Id<Arena> {
+impl<Arena, L: IdLayout> PartialEq for Id<Arena, L> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.raw.eq(&other.raw)
+    }
+}
+
+impl<Arena, L: IdLayout> Eq for Id<Arena, L> {}
+
+impl<Arena, L: IdLayout> Hash for Id<Arena, L> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.untyped().hash(state)
+    }
+}
+
+impl<Arena, L: IdLayout> Ord for Id<Arena, L> {
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering {
-        self.untyped.cmp(&other.untyped)
+        self.raw.cmp(&other.raw)
     }
 }
 
-impl<Arena> PartialOrd for Id<Arena> {
+impl<Arena, L: IdLayout> PartialOrd for Id<Arena, L> {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<Arena> Id<Arena> {
+impl<Arena, L: IdLayout> Id<Arena, L> {
     #[allow(dead_code)]
     #[cfg(not(feature = "id_creation"))]
     #[inline]
@@ -89,41 +124,73 @@ impl<Arena> Id<Arena> {
     #[cfg(feature = "id_creation")]
     #[inline]
     pub fn first(index: usize) -> Self {
-        Self::new(UntypedId::first_u32(index as u32))
+        Self::new(UntypedId::first(index))
     }
 
     #[cfg(not(feature = "id_creation"))]
     #[inline]
     pub(crate) fn new(id: UntypedId) -> Self {
         Id {
-            untyped: id,
+            raw: L::pack(id),
             marker: PhantomData,
         }
     }
 
+    /// Panics if the index or generation does not fit in the layout
     #[cfg(feature = "id_creation")]
     #[inline]
     pub fn new(id: UntypedId) -> Self {
+        assert!(L::fits(id), "Id does not fit in layout: {:?}", id);
         Id {
-            untyped: id,
+            raw: L::pack(id),
             marker: PhantomData,
         }
     }
 
     #[inline]
     pub fn index(self) -> usize {
-        self.untyped.index()
+        self.untyped().index()
+    }
+
+    #[inline]
+    pub fn untyped(self) -> UntypedId {
+        L::unpack(self.raw)
     }
 
     #[inline]
-    #[cfg(test)]
     pub(crate) fn gen(&self) -> Gen {
-        self.untyped.gen
+        self.untyped().gen
     }
 }
 
-impl<Arena: Fixed> ValidId for Id<Arena> {
+#[cfg(feature = "serde")]
+impl<Arena, L: IdLayout> serde::Serialize for Id<Arena, L> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&self.untyped(), serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Arena, L: IdLayout> serde::Deserialize<'de> for Id<Arena, L> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = <UntypedId as serde::Deserialize>::deserialize(deserializer)?;
+        if L::fits(id) {
+            Ok(Id {
+                raw: L::pack(id),
+                marker: PhantomData,
+            })
+        } else {
+            Err(serde::de::Error::custom(format_args!(
+                "Id does not fit in layout: {:?}",
+                id
+            )))
+        }
+    }
+}
+
+impl<Arena: Fixed, L: IdLayout> ValidId for Id<Arena, L> {
     type Arena = Arena;
+    type Layout = L;
 
     #[inline]
     fn index(self) -> usize {
@@ -131,13 +198,14 @@ impl<Arena: Fixed> ValidId for Id<Arena> {
     }
 
     #[inline]
-    fn id(self) -> Id<Arena> {
+    fn id(self) -> Id<Arena, L> {
         self
     }
 }
 
-impl<Arena: Fixed> ValidId for &Id<Arena> {
+impl<Arena: Fixed, L: IdLayout> ValidId for &Id<Arena, L> {
     type Arena = Arena;
+    type Layout = L;
 
     #[inline]
     fn index(self) -> usize {
@@ -145,7 +213,7 @@ impl<Arena: Fixed> ValidId for &Id<Arena> {
     }
 
     #[inline]
-    fn id(self) -> Id<Arena> {
+    fn id(self) -> Id<Arena, L> {
         *self
     }
 }
@@ -172,4 +240,15 @@ mod test {
         assert_eq!(0, id.index());
         assert_eq!(2, id.gen.get());
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_id_that_does_not_fit_layout() {
+        use crate::layout::Packed16x16;
+
+        let json = r#"{"index": 70000, "gen": 1}"#;
+
+        assert!(serde_json::from_str::<Id<()>>(json).is_ok());
+        assert!(serde_json::from_str::<Id<(), Packed16x16>>(json).is_err());
+    }
 }
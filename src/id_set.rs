@@ -0,0 +1,314 @@
+use crate::gen::Gen;
+use crate::id::UntypedId;
+use crate::layout::{IdLayout, Split32x32};
+use crate::{Fixed, Id, IdRange, Killed, Valid, Validator};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+
+/// A bitset over the index space of an arena.
+/// The generation of each member is kept alongside the bits, so stale Ids are never members.
+#[derive(Debug)]
+pub struct IdSet<Arena, L: IdLayout = Split32x32> {
+    bits: Vec<u64>,
+    gens: Vec<Gen>,
+    marker: PhantomData<Id<Arena, L>>,
+}
+
+impl<Arena, L: IdLayout> Default for IdSet<Arena, L> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            bits: Vec::new(),
+            gens: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Arena, L: IdLayout> Clone for IdSet<Arena, L> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            bits: self.bits.clone(),
+            gens: self.gens.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+#[inline]
+fn word_and_bit(index: usize) -> (usize, u64) {
+    (index / 64, 1 << (index % 64))
+}
+
+impl<Arena, L: IdLayout> IdSet<Arena, L> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns false if the Id was already a member.
+    /// Replaces an older generation of the same slot.
+    pub fn insert(&mut self, id: Id<Arena, L>) -> bool {
+        let index = id.index();
+        let (word, bit) = word_and_bit(index);
+        if word >= self.bits.len() {
+            self.bits.resize(word + 1, 0);
+        }
+        if index >= self.gens.len() {
+            self.gens.resize(index + 1, Gen::default());
+        }
+
+        let present = self.contains(id);
+        self.bits[word] |= bit;
+        self.gens[index] = id.gen();
+        !present
+    }
+
+    pub fn remove(&mut self, id: Id<Arena, L>) -> bool {
+        let present = self.contains(id);
+        if present {
+            let (word, bit) = word_and_bit(id.index());
+            self.bits[word] &= !bit;
+        }
+        present
+    }
+
+    #[inline]
+    pub fn contains(&self, id: Id<Arena, L>) -> bool {
+        let (word, bit) = word_and_bit(id.index());
+        match self.bits.get(word) {
+            Some(bits) if bits & bit != 0 => self.gens[id.index()] == id.gen(),
+            _ => false,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|bits| bits.count_ones() as usize)
+            .sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|bits| *bits == 0)
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.bits.clear();
+        self.gens.clear();
+    }
+
+    /// Iterates over the members in order of index
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = Id<Arena, L>> + '_ {
+        self.bits.iter().enumerate().flat_map(move |(word, &bits)| {
+            Ones(bits).map(move |bit| {
+                let index = word * 64 + bit;
+                Id::new(UntypedId {
+                    index: index as u32,
+                    gen: self.gens[index],
+                })
+            })
+        })
+    }
+
+    /// Iterates over the members that are alive according to the validator, in order of index
+    #[inline]
+    pub fn iter_valid<'a, 'valid, V>(
+        &'a self,
+        validator: V,
+    ) -> impl Iterator<Item = Valid<'valid, Id<Arena, L>>> + 'a
+    where
+        V: Validator<'valid, Arena, L> + 'a,
+    {
+        self.iter().filter_map(move |id| validator.validate(id))
+    }
+
+    /// Where both sets hold a different generation of the same slot, the newer generation is kept
+    pub fn union_with(&mut self, other: &Self) {
+        for id in other.iter() {
+            let (word, bit) = word_and_bit(id.index());
+            let newer = match self.bits.get(word) {
+                Some(bits) if bits & bit != 0 => self.gens[id.index()] < id.gen(),
+                _ => true,
+            };
+            if newer {
+                self.insert(id);
+            }
+        }
+    }
+
+    pub fn intersect_with(&mut self, other: &Self) {
+        for word in 0..self.bits.len() {
+            let mut bits = self.bits[word] & other.bits.get(word).copied().unwrap_or_default();
+            for bit in Ones(bits) {
+                let index = word * 64 + bit;
+                if self.gens[index] != other.gens[index] {
+                    bits &= !(1 << bit);
+                }
+            }
+            self.bits[word] = bits;
+        }
+    }
+
+    pub fn difference_with(&mut self, other: &Self) {
+        for id in other.iter() {
+            self.remove(id);
+        }
+    }
+}
+
+impl<Arena, L: IdLayout> Extend<Id<Arena, L>> for IdSet<Arena, L> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = Id<Arena, L>>>(&mut self, iter: I) {
+        for id in iter {
+            self.insert(id);
+        }
+    }
+}
+
+impl<Arena, L: IdLayout> FromIterator<Id<Arena, L>> for IdSet<Arena, L> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = Id<Arena, L>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<Arena: Fixed, L: IdLayout> From<IdRange<Arena, L>> for IdSet<Arena, L> {
+    #[inline]
+    fn from(range: IdRange<Arena, L>) -> Self {
+        range.into_iter().collect()
+    }
+}
+
+impl<Arena, L: IdLayout> From<&Killed<'_, Arena, L>> for IdSet<Arena, L> {
+    #[inline]
+    fn from(killed: &Killed<Arena, L>) -> Self {
+        killed.iter().map(|id| *id.value).collect()
+    }
+}
+
+/// The positions of the set bits of a word, from lowest to highest
+pub(crate) struct Ones(pub(crate) u64);
+
+impl Iterator for Ones {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+        let bit = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Allocator;
+
+    fn stale_and_new(allocator: &mut Allocator<()>) -> (Id<()>, Id<()>) {
+        let old = allocator.create().value;
+        allocator.kill(old);
+        let new = allocator.create().value;
+        assert_eq!(old.index(), new.index());
+        (old, new)
+    }
+
+    #[test]
+    fn stale_id_is_not_a_member() {
+        let mut allocator = Allocator::<()>::default();
+        let (old, new) = stale_and_new(&mut allocator);
+
+        let mut set = IdSet::new();
+        assert!(set.insert(old));
+
+        assert!(!set.contains(new));
+        assert!(!set.remove(new));
+
+        assert!(set.insert(new));
+        assert!(!set.contains(old));
+        assert_eq!(1, set.len());
+    }
+
+    #[test]
+    fn set_algebra() {
+        let mut allocator = Allocator::<()>::default();
+        let (old, new) = stale_and_new(&mut allocator);
+        let ids = (0..100)
+            .map(|_| allocator.create().value)
+            .collect::<Vec<_>>();
+
+        let a = ids[..60]
+            .iter()
+            .copied()
+            .chain(Some(old))
+            .collect::<IdSet<()>>();
+        let b = ids[40..]
+            .iter()
+            .copied()
+            .chain(Some(new))
+            .collect::<IdSet<()>>();
+
+        let mut union = a.clone();
+        union.union_with(&b);
+        assert_eq!(101, union.len());
+        assert!(union.contains(new));
+
+        let mut intersection = a.clone();
+        intersection.intersect_with(&b);
+        assert_eq!(ids[40..60], intersection.iter().collect::<Vec<_>>()[..]);
+
+        let mut difference = a.clone();
+        difference.difference_with(&b);
+        assert_eq!(41, difference.len());
+        assert!(difference.contains(old));
+    }
+
+    #[test]
+    fn iter_valid_in_index_order() {
+        let mut allocator = Allocator::<()>::default();
+        let ids = (0..130)
+            .map(|_| allocator.create().value)
+            .collect::<Vec<_>>();
+        allocator.kill(ids[64]);
+
+        let set = ids.iter().rev().copied().collect::<IdSet<()>>();
+        let valid = set
+            .iter_valid(&allocator)
+            .map(|id| id.value)
+            .collect::<Vec<_>>();
+
+        let expected = ids.iter().copied().filter(|id| id.index() != 64);
+        assert_eq!(expected.collect::<Vec<_>>(), valid);
+    }
+
+    #[test]
+    fn from_range_and_killed() {
+        #[derive(Debug)]
+        struct Fixed;
+        crate::fixed_id!(Fixed);
+
+        let mut allocator = Allocator::<Fixed>::default();
+        let range = allocator.create_range(4);
+        let set = IdSet::from(range);
+        assert_eq!(4, set.len());
+
+        let mut ids = range.into_iter().take(2).collect();
+        let killed = allocator.kill_multiple(&mut ids);
+        let set = IdSet::from(&killed);
+        assert_eq!(
+            range.into_iter().take(2).collect::<Vec<_>>(),
+            set.iter().collect::<Vec<_>>()
+        );
+    }
+}
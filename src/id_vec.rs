@@ -0,0 +1,204 @@
+use crate::layout::{IdLayout, Split32x32};
+use crate::{Fixed, Id, IdRange, ValidId};
+use std::ops::{Index, IndexMut};
+
+/// Dense storage with one slot per index, keyed by the Ids of a single arena.
+///
+/// Indexing with a `ValidId` skips the generation check, so values must be inserted
+/// whenever an Id is created to keep them in sync with the allocator.
+#[derive(Debug)]
+pub struct IdVec<Arena, T, L: IdLayout = Split32x32> {
+    ids: Vec<Option<Id<Arena, L>>>,
+    values: Vec<T>,
+}
+
+impl<Arena, T, L: IdLayout> Default for IdVec<Arena, T, L> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            ids: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<Arena, T: Clone, L: IdLayout> Clone for IdVec<Arena, T, L> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            ids: self.ids.clone(),
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<Arena, T, L: IdLayout> IdVec<Arena, T, L> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of slots, including any that have never been inserted into
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Grows the storage with default values if the index is past the end.
+    /// Returns the previous value if it was inserted with the same Id.
+    pub fn insert<V: ValidId<Arena = Arena, Layout = L>>(&mut self, id: V, value: T) -> Option<T>
+    where
+        T: Default,
+    {
+        let index = id.index();
+        if index >= self.values.len() {
+            self.ids.resize(index + 1, None);
+            self.values.resize_with(index + 1, T::default);
+        }
+
+        let previous = std::mem::replace(&mut self.values[index], value);
+        match self.ids[index].replace(id.id()) {
+            Some(old) if old == id.id() => Some(previous),
+            _ => None,
+        }
+    }
+
+    /// Returns `None` if no value was inserted for this Id, including when a different generation
+    /// was inserted into its slot
+    #[inline]
+    pub fn get(&self, id: Id<Arena, L>) -> Option<&T> {
+        match self.ids.get(id.index()) {
+            Some(Some(inserted)) if *inserted == id => self.values.get(id.index()),
+            _ => None,
+        }
+    }
+
+    /// Returns `None` if no value was inserted for this Id, including when a different generation
+    /// was inserted into its slot
+    #[inline]
+    pub fn get_mut(&mut self, id: Id<Arena, L>) -> Option<&mut T> {
+        match self.ids.get(id.index()) {
+            Some(Some(inserted)) if *inserted == id => self.values.get_mut(id.index()),
+            _ => None,
+        }
+    }
+
+    /// Iterates over the values that have been inserted, along with the Id they were inserted with
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (Id<Arena, L>, &T)> + '_ {
+        self.ids
+            .iter()
+            .zip(&self.values)
+            .filter_map(|(id, value)| id.map(|id| (id, value)))
+    }
+}
+
+impl<Arena, T, L: IdLayout, V: ValidId<Arena = Arena, Layout = L>> Index<V> for IdVec<Arena, T, L> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, id: V) -> &Self::Output {
+        &self.values[id.index()]
+    }
+}
+
+impl<Arena, T, L: IdLayout, V: ValidId<Arena = Arena, Layout = L>> IndexMut<V>
+    for IdVec<Arena, T, L>
+{
+    #[inline]
+    fn index_mut(&mut self, id: V) -> &mut Self::Output {
+        &mut self.values[id.index()]
+    }
+}
+
+impl<Arena: Fixed, T, L: IdLayout> Index<IdRange<Arena, L>> for IdVec<Arena, T, L> {
+    type Output = [T];
+
+    #[inline]
+    fn index(&self, range: IdRange<Arena, L>) -> &Self::Output {
+        &self.values[range.range().range()]
+    }
+}
+
+impl<Arena: Fixed, T, L: IdLayout> IndexMut<IdRange<Arena, L>> for IdVec<Arena, T, L> {
+    #[inline]
+    fn index_mut(&mut self, range: IdRange<Arena, L>) -> &mut Self::Output {
+        &mut self.values[range.range().range()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Allocator, Valid};
+
+    #[test]
+    fn get_given_stale_id_returns_none() {
+        let mut allocator = Allocator::<()>::default();
+        let mut values = IdVec::new();
+
+        let old = allocator.create().value;
+        values.insert(allocator.validate(old).unwrap(), 1);
+        allocator.kill(old);
+
+        let new = allocator.create().value;
+        assert_eq!(old.index(), new.index());
+        assert_eq!(None, values.insert(allocator.validate(new).unwrap(), 2));
+
+        assert_eq!(None, values.get(old));
+        assert_eq!(Some(&2), values.get(new));
+        assert_eq!(2, values[allocator.validate(new).unwrap()]);
+    }
+
+    #[test]
+    fn insert_grows_with_default() {
+        let mut allocator = Allocator::<()>::default();
+        let mut values = IdVec::new();
+
+        let skipped = allocator.create().value;
+        let id = allocator.create().value;
+        let valid = allocator.validate(id).unwrap();
+        values.insert(valid, 3);
+        values[valid] += 1;
+
+        assert_eq!(2, values.len());
+        assert_eq!(None, values.get(skipped));
+        assert_eq!(vec![(id, &4)], values.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn valid_index_passthrough() {
+        let mut allocator = Allocator::<()>::default();
+        let mut values = IdVec::new();
+        let id = allocator.create();
+        values.insert(id, 'a');
+
+        let valid = Valid::new(values);
+
+        assert_eq!('a', valid[id].value);
+    }
+
+    #[test]
+    fn index_range() {
+        #[derive(Debug)]
+        struct Fixed;
+        crate::fixed_id!(Fixed);
+
+        let mut allocator = Allocator::<Fixed>::default();
+        let mut values = IdVec::new();
+        let first = allocator.create_range(2);
+        let second = allocator.create_range(3);
+
+        for (i, id) in first.into_iter().chain(second).enumerate() {
+            values.insert(Valid::new(id), i);
+        }
+
+        assert_eq!(&[0, 1], &values[first]);
+        assert_eq!(&[2, 3, 4], &values[second]);
+    }
+}
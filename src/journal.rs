@@ -0,0 +1,677 @@
+use crate::alloc_gen::{AllocGen, ArenaGen, OutOfSync};
+use crate::allocator::{Entry, UntypedAllocator};
+use crate::id::UntypedId;
+use crate::layout::{IdLayout, Split32x32};
+use crate::{Allocator, Id, Killed, SlotReuse, Valid, Validator};
+use force_derive::*;
+use nonmax::NonMaxU32;
+use std::cmp::Reverse;
+
+/// The allocator fields an operation changes, other than its entries and quarantine
+#[derive(Debug, Copy, Clone)]
+struct Meta {
+    len: usize,
+    head: Option<NonMaxU32>,
+    tail: Option<NonMaxU32>,
+    retired: usize,
+}
+
+impl Meta {
+    #[inline]
+    fn of<L>(allocator: &UntypedAllocator<L>) -> Self {
+        Self {
+            len: allocator.entries.len(),
+            head: allocator.free.head,
+            tail: allocator.free.tail,
+            retired: allocator.retired,
+        }
+    }
+}
+
+/// An entry on either side of an operation, `None` where the index is past the end
+#[derive(Debug)]
+struct Change {
+    index: u32,
+    before: Option<Entry>,
+    after: Option<Entry>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Op {
+    Create,
+    Kill,
+    /// A tick that released quarantined slots
+    Release,
+}
+
+/// What one operation changed, and the Ids it created or killed
+#[derive(Debug)]
+struct Step {
+    op: Op,
+    changes: Vec<Change>,
+    before: Meta,
+    after: Meta,
+    /// Slots the operation put into quarantine
+    quarantined: Vec<(u64, u32)>,
+    /// Slots the operation took out of quarantine
+    released: Vec<(u64, u32)>,
+    ids: Vec<UntypedId>,
+}
+
+impl Step {
+    /// Whether the index is in the `LowestIndex` heap when its entry is `entry`
+    #[inline]
+    fn is_free(&self, index: u32, entry: &Option<Entry>, after: bool) -> bool {
+        let quarantined = if after {
+            &self.quarantined
+        } else {
+            &self.released
+        };
+        matches!(entry, Some(Entry::Dead(_))) && !quarantined.iter().any(|&(_, slot)| slot == index)
+    }
+
+    /// Whether moving to the given side of the step brings its Ids back to life
+    #[inline]
+    fn revives(&self, undo: bool) -> bool {
+        (self.op == Op::Kill) == undo
+    }
+}
+
+/// An allocator that records its operations so they can be undone and redone.
+///
+/// Undoing a kill revives the same Id, and undoing a create kills it and puts its slot back where it came from.
+/// The `AllocGen` is advanced rather than rewound, so undo and redo return the affected Ids
+/// for storages to catch up with.
+///
+/// Ticks that release quarantined slots are recorded too, and are crossed by undo and redo.
+#[derive(Debug, ForceDefault)]
+pub struct JournaledAllocator<Arena, L: IdLayout = Split32x32> {
+    allocator: Allocator<Arena, L>,
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+}
+
+impl<Arena, L: IdLayout> JournaledAllocator<Arena, L> {
+    /// Panics if the index space of the layout is exhausted
+    pub fn create(&mut self) -> Valid<'_, Id<Arena, L>> {
+        let untyped = &self.allocator.untyped;
+        let index = untyped.free.peek().unwrap_or(untyped.entries.len() as u32);
+
+        let ids = self.record(Op::Create, vec![index], |untyped| vec![untyped.create()]);
+        Valid::new(Id::new(ids[0]))
+    }
+
+    pub fn kill(&mut self, id: Id<Arena, L>) -> bool {
+        let id = id.untyped();
+        let ids = self.record(Op::Kill, vec![id.index], |untyped| {
+            if untyped.kill(id) {
+                vec![id]
+            } else {
+                Vec::new()
+            }
+        });
+        !ids.is_empty()
+    }
+
+    /// Recorded as a single operation
+    #[must_use]
+    pub fn kill_multiple(&mut self, ids: &mut Vec<Id<Arena, L>>) -> Killed<'_, Arena, L> {
+        let before = AllocGen::new(self.allocator.untyped.gen.clone());
+        let indices = ids.iter().map(|id| id.untyped().index).collect();
+        let killed = self.record(Op::Kill, indices, |untyped| {
+            untyped.kill_batch(ids.drain(..).map(Id::untyped))
+        });
+        let after = AllocGen::new(self.allocator.untyped.gen.clone());
+
+        let log = &self.allocator.untyped.kill_log;
+        Killed::new(
+            killed.into_iter().map(Id::new).collect(),
+            before,
+            after,
+            log,
+        )
+    }
+
+    /// Releases any quarantined slots whose delay has passed.
+    /// A tick that releases slots changes the free list, so it discards the redo history.
+    pub fn advance_tick(&mut self) {
+        let untyped = &self.allocator.untyped;
+        let tick = untyped.quarantine.tick + 1;
+        let delay = untyped.quarantine.delay;
+        let released = untyped
+            .quarantine
+            .slots
+            .iter()
+            .take_while(|&&(killed, _)| killed + delay <= tick)
+            .copied()
+            .collect::<Vec<_>>();
+
+        if released.is_empty() {
+            self.allocator.advance_tick();
+            return;
+        }
+
+        let indices = released.iter().map(|&(_, index)| index).collect();
+        let step = self.step(Op::Release, indices, released, |untyped| {
+            untyped.advance_tick();
+            Vec::new()
+        });
+        self.undo.push(step);
+        self.redo.clear();
+    }
+
+    #[inline]
+    pub fn tick(&self) -> u64 {
+        self.allocator.tick()
+    }
+
+    /// Records an operation if it created or killed any Ids
+    fn record<F>(&mut self, op: Op, indices: Vec<u32>, f: F) -> Vec<UntypedId>
+    where
+        F: FnOnce(&mut UntypedAllocator<L>) -> Vec<UntypedId>,
+    {
+        let step = self.step(op, indices, Vec::new(), f);
+        let ids = step.ids.clone();
+        if !ids.is_empty() {
+            self.undo.push(step);
+            self.redo.clear();
+        }
+        ids
+    }
+
+    /// Runs an operation that changes no entries other than those at `indices` and the tail of the free list,
+    /// and takes the `released` slots from the front of the quarantine
+    fn step<F>(&mut self, op: Op, mut indices: Vec<u32>, released: Vec<(u64, u32)>, f: F) -> Step
+    where
+        F: FnOnce(&mut UntypedAllocator<L>) -> Vec<UntypedId>,
+    {
+        let untyped = &mut self.allocator.untyped;
+        indices.extend(untyped.free.tail.map(|tail| tail.get()));
+        indices.sort_unstable();
+        indices.dedup();
+
+        let before = Meta::of(untyped);
+        let entries = indices
+            .iter()
+            .map(|&index| untyped.entries.get(index as usize).cloned())
+            .collect::<Vec<_>>();
+        let quarantined = untyped.quarantine.slots.len() - released.len();
+
+        let ids = f(untyped);
+
+        let changes = indices
+            .into_iter()
+            .zip(entries)
+            .map(|(index, before)| Change {
+                index,
+                before,
+                after: untyped.entries.get(index as usize).cloned(),
+            })
+            .filter(|change| change.before != change.after)
+            .collect();
+
+        Step {
+            op,
+            changes,
+            before,
+            after: Meta::of(untyped),
+            quarantined: untyped
+                .quarantine
+                .slots
+                .range(quarantined..)
+                .copied()
+                .collect(),
+            released,
+            ids,
+        }
+    }
+
+    /// Returns `None` if there is nothing to undo
+    #[must_use]
+    pub fn undo(&mut self) -> Option<Replayed<'_, Arena, L>> {
+        if !self.can_undo() {
+            return None;
+        }
+
+        while let Some(step) = self.undo.pop() {
+            self.apply(&step, true);
+            let done = step.op != Op::Release;
+            self.redo.push(step);
+            if done {
+                break;
+            }
+        }
+
+        let step = self.redo.last()?;
+        Some(self.replayed(step.ids.clone(), step.revives(true)))
+    }
+
+    /// Returns `None` if there is nothing to redo
+    #[must_use]
+    pub fn redo(&mut self) -> Option<Replayed<'_, Arena, L>> {
+        let step = self.redo.pop()?;
+        self.apply(&step, false);
+        let (ids, revived) = (step.ids.clone(), step.revives(false));
+        self.undo.push(step);
+
+        while matches!(self.redo.last(), Some(step) if step.op == Op::Release) {
+            let step = self.redo.pop()?;
+            self.apply(&step, false);
+            self.undo.push(step);
+        }
+
+        Some(self.replayed(ids, revived))
+    }
+
+    /// Advances the generation past the Ids, and logs them if they were killed
+    fn replayed(&mut self, ids: Vec<UntypedId>, revived: bool) -> Replayed<'_, Arena, L> {
+        let untyped = &mut self.allocator.untyped;
+        let before = untyped.gen.clone();
+        for id in &ids {
+            untyped.gen.increment_gen(*id);
+        }
+
+        if revived {
+            // revived Ids are not kills, so the log can no longer be replayed up to the generation
+            untyped.kill_log.clear();
+        } else {
+            untyped.kill_log.push(before.clone(), &ids);
+        }
+
+        let ids = ids.into_iter().map(Id::new).collect();
+        let before = AllocGen::new(before);
+        let after = AllocGen::new(untyped.gen.clone());
+        if revived {
+            Replayed::Revived(Revived::new(ids, before, after))
+        } else {
+            Replayed::Killed(Killed::new(ids, before, after, &untyped.kill_log))
+        }
+    }
+
+    /// Moves the allocator entries, free list and quarantine to one side of the step
+    fn apply(&mut self, step: &Step, undo: bool) {
+        let untyped = &mut self.allocator.untyped;
+        let (from, to) = if undo {
+            (step.after, step.before)
+        } else {
+            (step.before, step.after)
+        };
+
+        for _ in from.len..to.len {
+            untyped.entries.push(Entry::Retired);
+        }
+        for change in &step.changes {
+            let (old, new) = if undo {
+                (&change.after, &change.before)
+            } else {
+                (&change.before, &change.after)
+            };
+            if let Some(entry) = new {
+                untyped.entries[change.index as usize] = entry.clone();
+            }
+
+            if untyped.free.reuse == SlotReuse::LowestIndex {
+                let index = change.index;
+                match (
+                    step.is_free(index, old, !undo),
+                    step.is_free(index, new, undo),
+                ) {
+                    (true, false) => untyped.free.lowest.retain(|&Reverse(i)| i != index),
+                    (false, true) => untyped.free.lowest.push(Reverse(index)),
+                    _ => {}
+                }
+            }
+        }
+        untyped.entries.truncate(to.len);
+
+        untyped.free.head = to.head;
+        untyped.free.tail = to.tail;
+        untyped.retired = to.retired;
+
+        let slots = &mut untyped.quarantine.slots;
+        if undo {
+            slots.truncate(slots.len() - step.quarantined.len());
+            for &slot in step.released.iter().rev() {
+                slots.push_front(slot);
+            }
+        } else {
+            slots.drain(..step.released.len());
+            slots.extend(step.quarantined.iter().copied());
+        }
+    }
+
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        self.undo.iter().any(|step| step.op != Op::Release)
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    #[inline]
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    #[inline]
+    pub fn is_alive(&self, id: Id<Arena, L>) -> bool {
+        self.allocator.is_alive(id)
+    }
+
+    #[inline]
+    pub fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'_, Id<Arena, L>>> {
+        Validator::validate(&self, id)
+    }
+
+    #[inline]
+    pub fn ids(&self) -> impl Iterator<Item = Valid<'_, Id<Arena, L>>> + '_ {
+        self.allocator.ids()
+    }
+}
+
+/// The Ids affected by an undo or redo
+#[derive(Debug)]
+pub enum Replayed<'v, Arena, L: IdLayout = Split32x32> {
+    /// Ids that are dead again, logged as a batch of kills
+    Killed(Killed<'v, Arena, L>),
+    /// Ids that are alive again. These are not kills, so they are not logged.
+    Revived(Revived<'v, Arena, L>),
+}
+
+impl<'v, Arena, L: IdLayout> Replayed<'v, Arena, L> {
+    pub fn before(&self) -> &AllocGen<Arena> {
+        match self {
+            Replayed::Killed(killed) => killed.before(),
+            Replayed::Revived(revived) => revived.before(),
+        }
+    }
+
+    pub fn after(&self) -> &AllocGen<Arena> {
+        match self {
+            Replayed::Killed(killed) => killed.after(),
+            Replayed::Revived(revived) => revived.after(),
+        }
+    }
+
+    pub fn update_gen(&self, gen: &mut ArenaGen<Arena>) {
+        if let Err(error) = self.try_update_gen(gen) {
+            panic!("{}", error);
+        }
+    }
+
+    #[inline]
+    pub fn try_update_gen(&self, gen: &mut ArenaGen<Arena>) -> Result<(), OutOfSync> {
+        match self {
+            Replayed::Killed(killed) => killed.try_update_gen(gen),
+            Replayed::Revived(revived) => revived.try_update_gen(gen),
+        }
+    }
+}
+
+/// A list of valid, unique Ids that have been brought back to life by an undo or redo.
+/// Includes before and after allocator generations for validating and updating ArenaGen values
+#[derive(Debug)]
+pub struct Revived<'v, Arena, L: IdLayout = Split32x32> {
+    ids: Valid<'v, Vec<Id<Arena, L>>>,
+    before: AllocGen<Arena>,
+    after: AllocGen<Arena>,
+}
+
+impl<'v, Arena, L: IdLayout> Revived<'v, Arena, L> {
+    #[inline]
+    fn new(ids: Vec<Id<Arena, L>>, before: AllocGen<Arena>, after: AllocGen<Arena>) -> Self {
+        Self {
+            ids: Valid::new(ids),
+            before,
+            after,
+        }
+    }
+
+    pub fn before(&self) -> &AllocGen<Arena> {
+        &self.before
+    }
+
+    pub fn after(&self) -> &AllocGen<Arena> {
+        &self.after
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Valid<'v, &Id<Arena, L>>> {
+        (&self.ids).into_iter()
+    }
+
+    pub fn update_gen(&self, gen: &mut ArenaGen<Arena>) {
+        if let Err(error) = self.try_update_gen(gen) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Updates the generation if it has seen every earlier batch, otherwise describes how it is out of sync.
+    /// Revivals are not logged, so the number of missed batches is never known.
+    #[inline]
+    pub fn try_update_gen(&self, gen: &mut ArenaGen<Arena>) -> Result<(), OutOfSync> {
+        gen.try_update(&self.before, &self.after)
+    }
+}
+
+impl<Arena, L: IdLayout> From<Allocator<Arena, L>> for JournaledAllocator<Arena, L> {
+    #[inline]
+    fn from(mut allocator: Allocator<Arena, L>) -> Self {
+        allocator.flush();
+        Self {
+            allocator,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+/// The history is discarded
+impl<Arena, L: IdLayout> From<JournaledAllocator<Arena, L>> for Allocator<Arena, L> {
+    #[inline]
+    fn from(journaled: JournaledAllocator<Arena, L>) -> Self {
+        journaled.allocator
+    }
+}
+
+impl<Arena, L: IdLayout> AsRef<AllocGen<Arena>> for JournaledAllocator<Arena, L> {
+    #[inline]
+    fn as_ref(&self) -> &AllocGen<Arena> {
+        self.allocator.as_ref()
+    }
+}
+
+impl<'valid, Arena, L: IdLayout> Validator<'valid, Arena, L>
+    for &'valid JournaledAllocator<Arena, L>
+{
+    #[inline]
+    fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'valid, Id<Arena, L>>> {
+        self.is_alive(id).then(|| Valid::new(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ArenaGen;
+
+    #[test]
+    fn undo_kill_revives_same_id() {
+        let mut allocator = JournaledAllocator::<()>::default();
+        let ids = (0..4).map(|_| allocator.create().value).collect::<Vec<_>>();
+
+        let mut arena_gen = ArenaGen::<()>::default();
+        let killed = allocator.kill_multiple(&mut vec![ids[0]]);
+        killed.update_gen(&mut arena_gen);
+        let killed = allocator.kill_multiple(&mut vec![ids[1], ids[2]]);
+        killed.update_gen(&mut arena_gen);
+        assert!(arena_gen == *allocator.as_ref());
+
+        let undone = allocator.undo().unwrap();
+        match &undone {
+            Replayed::Revived(revived) => assert_eq!(2, revived.iter().count()),
+            Replayed::Killed(_) => panic!("undoing a kill revives its Ids"),
+        }
+        undone.update_gen(&mut arena_gen);
+        assert!(arena_gen == *allocator.as_ref());
+        assert!(ids[1..].iter().all(|id| allocator.is_alive(*id)));
+
+        // the free list is as it was before the kill, so the slot of ids[0] is reused next
+        let created = allocator.create().value;
+        assert_eq!(ids[0].index(), created.index());
+        assert!(!allocator.can_redo());
+    }
+
+    #[test]
+    fn undo_and_redo_create() {
+        let mut allocator = JournaledAllocator::<()>::default();
+        let first = allocator.create().value;
+        allocator.kill(first);
+        let second = allocator.create().value;
+
+        assert!(allocator.undo().is_some());
+        assert!(!allocator.is_alive(second));
+        assert!(allocator.undo().is_some());
+        assert!(allocator.is_alive(first));
+
+        assert!(allocator.redo().is_some());
+        assert!(allocator.redo().is_some());
+        assert!(allocator.redo().is_none());
+        assert!(!allocator.is_alive(first));
+        assert!(allocator.is_alive(second));
+
+        while allocator.undo().is_some() {}
+        assert_eq!(0, allocator.ids().count());
+        assert!(allocator.redo().is_some());
+        assert!(allocator.is_alive(first));
+    }
+
+    #[test]
+    fn undo_and_redo_restore_each_step() {
+        for &reuse in &[SlotReuse::Lifo, SlotReuse::Fifo, SlotReuse::LowestIndex] {
+            for quarantine in 0..2 {
+                let allocator = Allocator::<()>::default()
+                    .with_slot_reuse(reuse)
+                    .with_quarantine(quarantine)
+                    .with_kill_log(16);
+                let mut allocator = JournaledAllocator::from(allocator);
+                let mut arena_gen = ArenaGen::<()>::default();
+
+                let entries = |allocator: &JournaledAllocator<()>| {
+                    allocator.allocator.untyped.entries.clone()
+                };
+                let mut states = vec![entries(&allocator)];
+                let mut ids = Vec::new();
+                for _ in 0..6 {
+                    ids.push(allocator.create().value);
+                    states.push(entries(&allocator));
+                }
+                for kill in [vec![ids[4]], vec![ids[1], ids[3]]] {
+                    let killed = allocator.kill_multiple(&mut kill.clone());
+                    killed.update_gen(&mut arena_gen);
+                    states.push(entries(&allocator));
+                }
+                let _ = allocator.create();
+                states.push(entries(&allocator));
+
+                let mut undone = 0;
+                while let Some(killed) = allocator.undo() {
+                    killed.update_gen(&mut arena_gen);
+                    undone += 1;
+                    let state = &states[states.len() - 1 - undone];
+                    assert_eq!(state, &allocator.allocator.untyped.entries);
+                }
+                assert_eq!(states.len() - 1, undone);
+
+                while let Some(killed) = allocator.redo() {
+                    killed.update_gen(&mut arena_gen);
+                    undone -= 1;
+                    let state = &states[states.len() - 1 - undone];
+                    assert_eq!(state, &allocator.allocator.untyped.entries);
+                }
+                assert!(arena_gen == *allocator.as_ref());
+
+                // redoing the same steps reuses the same slots
+                let created = allocator.create().value;
+                let _ = allocator.undo();
+                assert_eq!(created, allocator.create().value);
+            }
+        }
+    }
+
+    #[test]
+    fn revived_ids_are_not_logged_as_kills() {
+        let allocator = Allocator::<()>::default().with_kill_log(4);
+        let mut allocator = JournaledAllocator::from(allocator);
+        let id = allocator.create().value;
+        let mut arena_gen = ArenaGen::<()>::default();
+        allocator
+            .kill_multiple(&mut vec![id])
+            .update_gen(&mut arena_gen);
+
+        match allocator.undo().unwrap() {
+            Replayed::Revived(revived) => revived.update_gen(&mut arena_gen),
+            Replayed::Killed(_) => panic!("undoing a kill revives its Ids"),
+        }
+        assert!(allocator.is_alive(id));
+        assert!(arena_gen == *allocator.as_ref());
+        assert!(allocator
+            .allocator
+            .killed_since(&arena_gen)
+            .unwrap()
+            .iter()
+            .next()
+            .is_none());
+        assert!(allocator
+            .allocator
+            .killed_since(&ArenaGen::default())
+            .is_err());
+
+        match allocator.redo().unwrap() {
+            Replayed::Killed(killed) => {
+                assert_eq!(
+                    vec![id],
+                    killed.iter().map(|id| *id.value).collect::<Vec<_>>()
+                );
+                killed.update_gen(&mut arena_gen);
+            }
+            Replayed::Revived(_) => panic!("redoing a kill kills its Ids"),
+        }
+        assert!(!allocator.is_alive(id));
+        assert!(arena_gen == *allocator.as_ref());
+    }
+
+    #[test]
+    fn advance_tick_releases_quarantined_slots() {
+        for &reuse in &[SlotReuse::Lifo, SlotReuse::Fifo, SlotReuse::LowestIndex] {
+            let allocator = Allocator::<()>::default()
+                .with_slot_reuse(reuse)
+                .with_quarantine(1);
+            let mut allocator = JournaledAllocator::from(allocator);
+            let ids = (0..2).map(|_| allocator.create().value).collect::<Vec<_>>();
+
+            allocator.kill(ids[0]);
+            assert_eq!(2, allocator.create().value.index());
+            allocator.advance_tick();
+            assert_eq!(1, allocator.tick());
+            let reused = allocator.create().value;
+            assert_eq!(0, reused.index());
+
+            // undo crosses the tick, putting the slot back into quarantine
+            assert!(allocator.undo().is_some());
+            assert!(allocator.undo().is_some());
+            assert_eq!(1, allocator.allocator.quarantined());
+            assert!(allocator.undo().is_some());
+            assert!(allocator.is_alive(ids[0]));
+            assert_eq!(0, allocator.allocator.quarantined());
+
+            while allocator.redo().is_some() {}
+            assert!(allocator.is_alive(reused));
+            assert!(!allocator.can_redo());
+            assert_eq!(0, allocator.allocator.quarantined());
+            assert_eq!(3, allocator.create().value.index());
+        }
+    }
+}
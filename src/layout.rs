@@ -0,0 +1,132 @@
+use crate::gen::Gen;
+use crate::id::UntypedId;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::num::NonZeroU32;
+
+/// Determines how the index and generation of an `Id` are stored, and how large each can get.
+///
+/// Every layout keeps a niche so that `Option<Id>` is the same size as `Id`.
+///
+/// The allocator stores indices as `u32`, so no layout has a wider index.
+/// A 48-bit index packed with a 16-bit generation is not supported.
+pub trait IdLayout: private::Sealed {
+    type Raw: Debug + Copy + Eq + Ord + Hash;
+
+    /// The largest index an allocator using this layout will hand out
+    const MAX_INDEX: u32;
+
+    /// The largest generation a slot can reach before it is exhausted
+    const MAX_GEN: u32;
+
+    fn pack(id: UntypedId) -> Self::Raw;
+
+    fn unpack(raw: Self::Raw) -> UntypedId;
+
+    #[inline]
+    fn fits(id: UntypedId) -> bool {
+        id.index <= Self::MAX_INDEX && id.gen.get() <= Self::MAX_GEN
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::Split32x32 {}
+    impl Sealed for super::Packed16x16 {}
+}
+
+/// 32-bit index and 32-bit generation stored as separate fields. This is the default layout.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Split32x32;
+
+impl IdLayout for Split32x32 {
+    type Raw = UntypedId;
+
+    // u32::MAX is reserved for the end of the free list
+    const MAX_INDEX: u32 = u32::MAX - 1;
+    const MAX_GEN: u32 = u32::MAX;
+
+    #[inline]
+    fn pack(id: UntypedId) -> Self::Raw {
+        id
+    }
+
+    #[inline]
+    fn unpack(raw: Self::Raw) -> UntypedId {
+        raw
+    }
+}
+
+/// 16-bit index and 16-bit generation packed into a single `NonZeroU32`, for small arenas
+/// that are referenced in large numbers
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Packed16x16;
+
+impl IdLayout for Packed16x16 {
+    type Raw = NonZeroU32;
+
+    const MAX_INDEX: u32 = u16::MAX as u32;
+    const MAX_GEN: u32 = u16::MAX as u32;
+
+    #[inline]
+    fn pack(id: UntypedId) -> Self::Raw {
+        debug_assert!(Self::fits(id));
+        let bits = id.index << 16 | id.gen.get();
+        NonZeroU32::new(bits).unwrap()
+    }
+
+    #[inline]
+    fn unpack(raw: Self::Raw) -> UntypedId {
+        let bits = raw.get();
+        UntypedId {
+            index: bits >> 16,
+            gen: Gen::new(bits & 0xffff).unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Id;
+    use std::mem::size_of;
+
+    fn round_trip<L: IdLayout>() {
+        let id = UntypedId {
+            index: L::MAX_INDEX,
+            gen: Gen::new(L::MAX_GEN).unwrap(),
+        };
+
+        assert_eq!(id, L::unpack(L::pack(id)));
+
+        let id = UntypedId::first(0);
+
+        assert_eq!(id, L::unpack(L::pack(id)));
+    }
+
+    #[test]
+    fn split_round_trip() {
+        round_trip::<Split32x32>();
+    }
+
+    #[test]
+    fn packed_16_round_trip() {
+        round_trip::<Packed16x16>();
+    }
+
+    #[test]
+    fn option_niche() {
+        assert_eq!(8, size_of::<Option<Id<(), Split32x32>>>());
+        assert_eq!(4, size_of::<Option<Id<(), Packed16x16>>>());
+    }
+
+    #[test]
+    fn packed_ordering_matches_split() {
+        let a = UntypedId::first(1);
+        let mut b = UntypedId::first(0);
+        b.increment_gen();
+
+        assert!(Packed16x16::pack(b) < Packed16x16::pack(a));
+    }
+}
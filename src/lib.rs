@@ -1,25 +1,71 @@
 pub use ref_cast::RefCast;
 pub use static_assertions::assert_impl_one;
 
-pub use alloc_gen::{AllocGen, ArenaGen};
-pub use allocator::{Allocator, Killed, RangeAllocator};
+pub use alloc_gen::{AllocGen, ArenaGen, KilledSinceError, OutOfSync};
+pub use allocator::{Allocator, Killed, RangeAllocator, SlotReuse};
+pub use checkpoint::Checkpoint;
+pub use commands::{Commands, DuplicateKey, KeyedCreates};
+pub use concurrent::ConcurrentAllocator;
+pub use fork::{Fork, ForkConflict};
+pub use frozen::FrozenAllocator;
+pub use gen::GenExhaustion;
+pub use hash::{IdBuildHasher, IdHashMap, IdHashSet, IdHasher, RetainAlive};
 pub use id::Id;
+pub use id_set::IdSet;
+pub use id_vec::IdVec;
+pub use journal::{JournaledAllocator, Replayed, Revived};
+pub use layout::{IdLayout, Packed16x16, Split32x32};
 pub use range::IdRange;
+pub use remap::IdRemap;
+pub use shared::{AllocatorReader, ReadGen, SharedAllocator};
+pub use sparse_set::SparseSet;
 pub use traits::*;
 pub use valid::Valid;
+#[cfg(feature = "serde")]
+pub use valid_seed::{DeadIdPolicy, ValidSeed};
 
 #[cfg(feature = "untyped")]
 pub mod untyped {
     pub use crate::alloc_gen::{UntypedAllocGen, UntypedArenaGen};
     pub use crate::allocator::UntypedAllocator;
+    pub use crate::checkpoint::UntypedCheckpoint;
+    pub use crate::concurrent::UntypedConcurrentAllocator;
     pub use crate::id::UntypedId;
     pub use crate::range::UntypedIdRange;
+    pub use crate::remap::UntypedIdRemap;
 }
 
 mod alloc_gen;
 mod allocator;
+mod checkpoint;
+mod commands;
+mod concurrent;
+mod entries;
+mod fork;
+mod frozen;
 mod gen;
+mod hash;
 mod id;
+mod id_set;
+mod id_vec;
+mod journal;
+mod layout;
 pub mod range;
+mod remap;
+mod shared;
+mod sparse_set;
 mod traits;
 mod valid;
+#[cfg(feature = "serde")]
+mod valid_seed;
+
+// Handles do not own an arena, so they are Send + Sync whatever the arena marker is
+static_assertions::assert_impl_all!(Id<*const ()>: Send, Sync);
+static_assertions::assert_impl_all!(IdRange<*const ()>: Send, Sync);
+static_assertions::assert_impl_all!(range::Iter<*const ()>: Send, Sync);
+static_assertions::assert_impl_all!(RangeAllocator<*const ()>: Send, Sync);
+static_assertions::assert_impl_all!(Allocator<*const ()>: Send, Sync);
+static_assertions::assert_impl_all!(ConcurrentAllocator<*const ()>: Send, Sync);
+static_assertions::assert_impl_all!(AllocatorReader<*const ()>: Send, Sync);
+static_assertions::assert_impl_all!(FrozenAllocator<*const ()>: Send, Sync);
+static_assertions::assert_impl_all!(Valid<Id<*const ()>>: Send, Sync);
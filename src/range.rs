@@ -1,10 +1,16 @@
 use super::*;
 use crate::id::UntypedId;
+use crate::layout::{IdLayout, Split32x32};
 use force_derive::*;
 use std::marker::PhantomData;
 use std::ops::Range;
 
 #[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "serde_impl::UntypedIdRangeData")
+)]
 pub struct UntypedIdRange {
     pub(crate) start: usize,
     pub(crate) end: usize,
@@ -96,12 +102,18 @@ impl IntoIterator for UntypedIdRange {
 }
 
 #[derive(Debug, ForceDefault, ForceCopy, ForceClone, ForceEq, ForcePartialEq, ForceHash)]
-pub struct IdRange<Arena> {
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent, bound = "")
+)]
+pub struct IdRange<Arena, L = Split32x32> {
     pub(crate) range: UntypedIdRange,
-    arena: PhantomData<*const Arena>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    arena: PhantomData<fn() -> (Arena, L)>,
 }
 
-impl<Arena: Fixed> From<UntypedIdRange> for IdRange<Arena> {
+impl<Arena: Fixed, L: IdLayout> From<UntypedIdRange> for IdRange<Arena, L> {
     #[inline]
     fn from(range: UntypedIdRange) -> Self {
         Self {
@@ -111,14 +123,14 @@ impl<Arena: Fixed> From<UntypedIdRange> for IdRange<Arena> {
     }
 }
 
-impl<Arena: Fixed> From<Id<Arena>> for IdRange<Arena> {
+impl<Arena: Fixed, L: IdLayout> From<Id<Arena, L>> for IdRange<Arena, L> {
     #[inline]
-    fn from(id: Id<Arena>) -> Self {
-        UntypedIdRange::from(id.untyped).into()
+    fn from(id: Id<Arena, L>) -> Self {
+        UntypedIdRange::from(id.untyped()).into()
     }
 }
 
-impl<Arena: Fixed> IdRange<Arena> {
+impl<Arena: Fixed, L: IdLayout> IdRange<Arena, L> {
     #[cfg(not(feature = "id_creation"))]
     #[inline]
     pub(crate) fn new(start: usize, end: usize) -> Self {
@@ -147,19 +159,19 @@ impl<Arena: Fixed> IdRange<Arena> {
     }
 
     #[inline]
-    pub fn extend<V: ValidId<Arena = Arena>>(&mut self, id: V) {
-        self.range.extend(id.id().untyped)
+    pub fn extend<V: ValidId<Arena = Arena, Layout = L>>(&mut self, id: V) {
+        self.range.extend(id.id().untyped())
     }
 
     #[inline]
-    pub fn position(self, id: Id<Arena>) -> Option<usize> {
-        self.range.position(id.untyped)
+    pub fn position(self, id: Id<Arena, L>) -> Option<usize> {
+        self.range.position(id.untyped())
     }
 }
 
-impl<Arena> IntoIterator for IdRange<Arena> {
-    type Item = Id<Arena>;
-    type IntoIter = Iter<Arena>;
+impl<Arena, L: IdLayout> IntoIterator for IdRange<Arena, L> {
+    type Item = Id<Arena, L>;
+    type IntoIter = Iter<Arena, L>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -170,18 +182,20 @@ impl<Arena> IntoIterator for IdRange<Arena> {
     }
 }
 
-impl<Arena: Fixed> ValidRange for IdRange<Arena> {
+impl<Arena: Fixed, L: IdLayout> ValidRange for IdRange<Arena, L> {
     type Arena = Arena;
+    type Layout = L;
     #[inline]
-    fn range(self) -> IdRange<Arena> {
+    fn range(self) -> IdRange<Arena, L> {
         self
     }
 }
 
-impl<Arena: Fixed> ValidRange for &IdRange<Arena> {
+impl<Arena: Fixed, L: IdLayout> ValidRange for &IdRange<Arena, L> {
     type Arena = Arena;
+    type Layout = L;
     #[inline]
-    fn range(self) -> IdRange<Arena> {
+    fn range(self) -> IdRange<Arena, L> {
         *self
     }
 }
@@ -201,17 +215,63 @@ impl Iterator for UntypedIter {
 }
 
 #[derive(Debug, ForceClone)]
-pub struct Iter<Arena> {
+pub struct Iter<Arena, L = Split32x32> {
     iter: UntypedIter,
-    arena: PhantomData<*const Arena>,
+    arena: PhantomData<fn() -> (Arena, L)>,
 }
 
-impl<Arena> Iterator for Iter<Arena> {
-    type Item = Id<Arena>;
+/// Panics if an index does not fit in the layout
+impl<Arena, L: IdLayout> Iterator for Iter<Arena, L> {
+    type Item = Id<Arena, L>;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(Id::new)
+        let id = self.iter.next()?;
+        assert!(id.index <= L::MAX_INDEX, "Id index out of range for layout");
+        Some(Id::new(id))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::*;
+    use std::convert::TryFrom;
+    use std::fmt::{Display, Formatter};
+
+    /// Unvalidated range, checked before it is turned into an `UntypedIdRange`
+    #[derive(serde::Deserialize)]
+    pub(super) struct UntypedIdRangeData {
+        start: usize,
+        end: usize,
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub(super) struct InvertedRange {
+        start: usize,
+        end: usize,
+    }
+
+    impl Display for InvertedRange {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "range start {} is after its end {}",
+                self.start, self.end
+            )
+        }
+    }
+
+    impl TryFrom<UntypedIdRangeData> for UntypedIdRange {
+        type Error = InvertedRange;
+
+        fn try_from(
+            UntypedIdRangeData { start, end }: UntypedIdRangeData,
+        ) -> Result<Self, Self::Error> {
+            if start > end {
+                return Err(InvertedRange { start, end });
+            }
+            Ok(UntypedIdRange { start, end })
+        }
     }
 }
 
@@ -273,4 +333,28 @@ mod test {
         assert_eq!(Some(4), range.position(UntypedId::first(9)));
         assert_eq!(None, range.position(UntypedId::first(10)));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_inverted_range() {
+        let range = serde_json::from_str::<IdRange<Fixed>>(r#"{"start": 2, "end": 5}"#);
+        assert_eq!(IdRange::new(2, 5), range.unwrap());
+
+        let error = serde_json::from_str::<IdRange<Fixed>>(r#"{"start": 5, "end": 2}"#);
+        assert_eq!(
+            "range start 5 is after its end 2",
+            error.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Id index out of range for layout")]
+    fn iter_past_layout_index_space() {
+        use crate::layout::Packed16x16;
+
+        let range = IdRange::<Fixed, Packed16x16>::new(u16::MAX as usize, u16::MAX as usize + 2);
+        let mut iter = range.into_iter();
+        assert_eq!(u16::MAX as usize, iter.next().unwrap().index());
+        iter.next();
+    }
 }
@@ -0,0 +1,153 @@
+use crate::id::UntypedId;
+use crate::layout::{IdLayout, Split32x32};
+use crate::{Id, Valid, Validator};
+use force_derive::*;
+use std::marker::PhantomData;
+
+/// Maps old Ids to the new Ids that replaced them, indexed by the old index
+#[derive(Debug, Default, Clone)]
+pub struct UntypedIdRemap {
+    map: Vec<Option<(UntypedId, UntypedId)>>,
+    len: usize,
+}
+
+impl UntypedIdRemap {
+    #[inline]
+    pub(crate) fn insert(&mut self, old: UntypedId, new: UntypedId) {
+        let index = old.index();
+        if index >= self.map.len() {
+            self.map.resize(index + 1, None);
+        }
+
+        if self.map[index].replace((old, new)).is_none() {
+            self.len += 1;
+        }
+    }
+
+    /// Returns `None` if the Id was not remapped, including when the generation does not match
+    #[inline]
+    pub fn get(&self, old: UntypedId) -> Option<UntypedId> {
+        match self.map.get(old.index()) {
+            Some(&Some((id, new))) if id == old => Some(new),
+            _ => None,
+        }
+    }
+
+    /// Iterates over `(old, new)` pairs in order of the old index
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (UntypedId, UntypedId)> + '_ {
+        self.map.iter().filter_map(|pair| *pair)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[derive(Debug, ForceDefault, ForceClone)]
+pub struct IdRemap<Arena, L = Split32x32> {
+    untyped: UntypedIdRemap,
+    arena: PhantomData<fn() -> (Arena, L)>,
+}
+
+impl<Arena, L: IdLayout> IdRemap<Arena, L> {
+    #[inline]
+    pub(crate) fn new(untyped: UntypedIdRemap) -> Self {
+        Self {
+            untyped,
+            arena: PhantomData,
+        }
+    }
+
+    /// Returns `None` if the Id was not remapped, including when the generation does not match
+    #[inline]
+    pub fn get(&self, old: Id<Arena, L>) -> Option<Id<Arena, L>> {
+        self.untyped.get(old.untyped()).map(Id::new)
+    }
+
+    /// Returns the new Id if it is alive according to the validator
+    #[inline]
+    pub fn get_valid<'valid, V: Validator<'valid, Arena, L>>(
+        &self,
+        old: Id<Arena, L>,
+        validator: V,
+    ) -> Option<Valid<'valid, Id<Arena, L>>> {
+        self.get(old).and_then(|new| validator.validate(new))
+    }
+
+    /// Iterates over `(old, new)` pairs in order of the old index
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (Id<Arena, L>, Id<Arena, L>)> + '_ {
+        self.untyped
+            .iter()
+            .map(|(old, new)| (Id::new(old), Id::new(new)))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.untyped.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.untyped.is_empty()
+    }
+
+    #[inline]
+    pub fn untyped(&self) -> &UntypedIdRemap {
+        &self.untyped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_given_stale_gen_returns_none() {
+        let mut remap = UntypedIdRemap::default();
+        let mut old = UntypedId::first(3);
+        let new = UntypedId::first(0);
+        remap.insert(old, new);
+
+        assert_eq!(Some(new), remap.get(old));
+
+        old.increment_gen();
+        assert_eq!(None, remap.get(old));
+        assert_eq!(None, remap.get(UntypedId::first(1)));
+        assert_eq!(1, remap.len());
+    }
+
+    #[test]
+    fn import_from() {
+        use crate::Allocator;
+
+        let mut scratch = Allocator::<()>::default();
+        let ids = (0..3).map(|_| scratch.create().value).collect::<Vec<_>>();
+        scratch.kill(ids[1]);
+
+        let mut world = Allocator::<()>::default();
+        let existing = world.create().value;
+
+        let remap = world.import_from(&scratch);
+
+        assert_eq!(2, remap.len());
+        assert_eq!(None, remap.get(ids[1]));
+
+        let imported = remap.get_valid(ids[2], &world).unwrap();
+        assert_eq!(2, imported.value.index());
+        assert_ne!(existing, remap.get(ids[0]).unwrap());
+
+        let pairs = remap.iter().collect::<Vec<_>>();
+        assert_eq!(
+            vec![ids[0], ids[2]],
+            pairs.iter().map(|p| p.0).collect::<Vec<_>>()
+        );
+    }
+}
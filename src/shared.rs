@@ -0,0 +1,347 @@
+use crate::alloc_gen::AllocGen;
+use crate::allocator::Entry;
+use crate::id::UntypedId;
+use crate::layout::{IdLayout, Split32x32};
+use crate::{Allocator, Id, Killed, Valid, Validator};
+use force_derive::*;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+const ALIVE: u64 = 1 << 32;
+
+/// The first chunk holds this many slots, and each chunk after it is twice the size of the last
+const FIRST_CHUNK: usize = 64;
+
+/// Enough chunks to cover every `u32` index
+const CHUNKS: usize = 27;
+
+#[inline]
+fn chunk_and_offset(index: usize) -> (usize, usize) {
+    let chunk = (index / FIRST_CHUNK + 1).ilog2() as usize;
+    let start = FIRST_CHUNK * ((1 << chunk) - 1);
+    (chunk, index - start)
+}
+
+/// Atomic slots held in chunks that are never moved once allocated, so they can be read without a lock
+#[derive(Debug)]
+pub(crate) struct AtomicSlots([OnceLock<Box<[AtomicU64]>>; CHUNKS]);
+
+impl Default for AtomicSlots {
+    #[inline]
+    fn default() -> Self {
+        Self(std::array::from_fn(|_| OnceLock::new()))
+    }
+}
+
+impl AtomicSlots {
+    #[inline]
+    pub(crate) fn get(&self, index: usize) -> Option<&AtomicU64> {
+        let (chunk, offset) = chunk_and_offset(index);
+        self.0.get(chunk)?.get().map(|chunk| &chunk[offset])
+    }
+
+    /// Allocates the chunk that holds the slot if it does not exist yet, with every slot set to zero
+    pub(crate) fn get_or_init(&self, index: usize) -> &AtomicU64 {
+        let (chunk, offset) = chunk_and_offset(index);
+        let slots = self.0[chunk].get_or_init(|| {
+            let len = FIRST_CHUNK << chunk;
+            (0..len).map(|_| AtomicU64::new(0)).collect()
+        });
+        &slots[offset]
+    }
+}
+
+/// The liveness of each slot, published by the writer
+#[derive(Debug, Default)]
+struct Slots {
+    slots: AtomicSlots,
+    /// Odd while the writer is storing to the slots, and advanced to the next even value once the change is published
+    gen: AtomicU64,
+}
+
+impl Slots {
+    /// Only called by the writer
+    fn set(&self, id: UntypedId, alive: bool) {
+        let slot = if alive {
+            id.gen.get() as u64 | ALIVE
+        } else {
+            id.gen.get() as u64
+        };
+        self.slots
+            .get_or_init(id.index())
+            .store(slot, Ordering::Release);
+    }
+
+    /// Called by the writer before storing to any slot
+    #[inline]
+    fn begin(&self) {
+        self.gen.fetch_add(1, Ordering::Release);
+    }
+
+    /// Called by the writer after the last slot of a change is stored
+    #[inline]
+    fn end(&self) {
+        self.gen.fetch_add(1, Ordering::Release);
+    }
+
+    #[inline]
+    fn is_alive(&self, id: UntypedId) -> bool {
+        let alive = id.gen.get() as u64 | ALIVE;
+        matches!(self.slots.get(id.index()), Some(slot) if slot.load(Ordering::Acquire) == alive)
+    }
+}
+
+/// An allocator with a single writer whose liveness can be checked from other threads without locking.
+///
+/// Readers are handed out by [`SharedAllocator::reader`].
+#[derive(Debug)]
+pub struct SharedAllocator<Arena, L: IdLayout = Split32x32> {
+    allocator: Allocator<Arena, L>,
+    slots: Arc<Slots>,
+}
+
+impl<Arena, L: IdLayout> Default for SharedAllocator<Arena, L> {
+    #[inline]
+    fn default() -> Self {
+        Self::from(Allocator::default())
+    }
+}
+
+impl<Arena, L: IdLayout> SharedAllocator<Arena, L> {
+    /// Panics if the index space of the layout is exhausted
+    #[inline]
+    pub fn create(&mut self) -> Valid<'_, Id<Arena, L>> {
+        self.try_create().expect("Id index space exhausted")
+    }
+
+    /// Returns `None` if the index space of the layout is exhausted
+    #[inline]
+    pub fn try_create(&mut self) -> Option<Valid<'_, Id<Arena, L>>> {
+        let id = self.allocator.try_create()?.value;
+        self.slots.begin();
+        self.slots.set(id.untyped(), true);
+        self.slots.end();
+        Some(Valid::new(id))
+    }
+
+    #[inline]
+    pub fn kill(&mut self, id: Id<Arena, L>) -> bool {
+        let killed = self.allocator.kill(id);
+        if killed {
+            self.slots.begin();
+            self.slots.set(id.untyped(), false);
+            self.slots.end();
+        }
+        killed
+    }
+
+    #[must_use]
+    pub fn kill_multiple(&mut self, ids: &mut Vec<Id<Arena, L>>) -> Killed<'_, Arena, L> {
+        let killed = self.allocator.kill_multiple(ids);
+        self.slots.begin();
+        for id in killed.iter() {
+            self.slots.set(id.value.untyped(), false);
+        }
+        self.slots.end();
+        killed
+    }
+
+    #[inline]
+    pub fn is_alive(&self, id: Id<Arena, L>) -> bool {
+        self.allocator.is_alive(id)
+    }
+
+    #[inline]
+    pub fn reader(&self) -> AllocatorReader<Arena, L> {
+        AllocatorReader {
+            slots: self.slots.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Arena, L: IdLayout> From<Allocator<Arena, L>> for SharedAllocator<Arena, L> {
+    fn from(mut allocator: Allocator<Arena, L>) -> Self {
+        allocator.flush();
+
+        let slots = Slots::default();
+        for entry in allocator.untyped.entries.iter() {
+            if let Entry::Alive(id) = entry {
+                slots.set(*id, true);
+            }
+        }
+
+        Self {
+            allocator,
+            slots: Arc::new(slots),
+        }
+    }
+}
+
+/// Existing readers stop seeing changes
+impl<Arena, L: IdLayout> From<SharedAllocator<Arena, L>> for Allocator<Arena, L> {
+    #[inline]
+    fn from(shared: SharedAllocator<Arena, L>) -> Self {
+        shared.allocator
+    }
+}
+
+impl<Arena, L> AsRef<AllocGen<Arena>> for SharedAllocator<Arena, L>
+where
+    L: IdLayout,
+{
+    #[inline]
+    fn as_ref(&self) -> &AllocGen<Arena> {
+        self.allocator.as_ref()
+    }
+}
+
+impl<'valid, Arena, L: IdLayout> Validator<'valid, Arena, L> for &'valid SharedAllocator<Arena, L> {
+    #[inline]
+    fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'valid, Id<Arena, L>>> {
+        self.is_alive(id).then(|| Valid::new(id))
+    }
+}
+
+/// Checks liveness on another thread while the writer keeps going.
+///
+/// Does not impl `Validator` as an Id can be killed right after it is checked.
+#[derive(Debug, ForceClone)]
+pub struct AllocatorReader<Arena, L = Split32x32> {
+    slots: Arc<Slots>,
+    marker: PhantomData<fn() -> (Arena, L)>,
+}
+
+impl<Arena, L: IdLayout> AllocatorReader<Arena, L> {
+    #[inline]
+    pub fn is_alive(&self, id: Id<Arena, L>) -> bool {
+        self.slots.is_alive(id.untyped())
+    }
+
+    /// Changes whenever the writer creates or kills an Id
+    #[inline]
+    pub fn gen(&self) -> ReadGen<Arena> {
+        ReadGen(self.slots.gen.load(Ordering::Acquire), PhantomData)
+    }
+}
+
+/// A token from [`AllocatorReader::gen`].
+/// If it is unchanged after a set of reads, the writer made no changes during them.
+///
+/// A token taken while the writer is partway through a change never compares equal, not even to itself.
+#[derive(Debug, ForceCopy, ForceClone)]
+pub struct ReadGen<Arena>(u64, PhantomData<fn() -> Arena>);
+
+impl<Arena> PartialEq for ReadGen<Arena> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 & 1 == 0 && self.0 == other.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn chunk_and_offset_cover_every_index() {
+        assert_eq!((0, 0), chunk_and_offset(0));
+        assert_eq!((0, 63), chunk_and_offset(63));
+        assert_eq!((1, 0), chunk_and_offset(64));
+        assert_eq!((1, 127), chunk_and_offset(191));
+        assert_eq!((2, 0), chunk_and_offset(192));
+
+        let (chunk, offset) = chunk_and_offset(u32::MAX as usize);
+        assert!(chunk < CHUNKS);
+        assert!(offset < FIRST_CHUNK << chunk);
+    }
+
+    #[test]
+    fn reader_sees_writer_changes() {
+        let mut allocator = Allocator::<()>::default();
+        let before = allocator.create().value;
+        let mut shared = SharedAllocator::from(allocator);
+        let reader = shared.reader();
+        assert!(reader.is_alive(before));
+
+        let gen = reader.gen();
+        let ids = (0..200).map(|_| shared.create().value).collect::<Vec<_>>();
+        assert_ne!(gen, reader.gen());
+        assert!(ids.iter().all(|id| reader.is_alive(*id)));
+
+        let gen = reader.gen();
+        let killed = shared.kill_multiple(&mut vec![before, ids[150], ids[150]]);
+        assert_eq!(2, killed.iter().count());
+        assert_ne!(gen, reader.gen());
+        assert!(!reader.is_alive(before));
+        assert!(!reader.is_alive(ids[150]));
+
+        let reused = shared.create().value;
+        assert!(reader.is_alive(reused));
+        assert!(!reader.is_alive(before));
+        assert!(!reader.is_alive(ids[150]));
+    }
+
+    #[test]
+    fn read_while_writing() {
+        let mut shared = SharedAllocator::<()>::default();
+        let kept = shared.create().value;
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let reader = shared.reader();
+                let done = &done;
+                scope.spawn(move || {
+                    while !done.load(Ordering::Relaxed) {
+                        assert!(reader.is_alive(kept));
+                    }
+                });
+            }
+
+            for _ in 0..1000 {
+                let id = shared.create().value;
+                shared.kill(id);
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+
+        assert_eq!(Allocator::from(shared).ids().count(), 1,);
+    }
+
+    #[test]
+    fn unchanged_gen_means_batch_was_not_torn() {
+        let mut shared = SharedAllocator::<()>::default();
+        let batches = (0..1000)
+            .map(|_| (0..32).map(|_| shared.create().value).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let reader = shared.reader();
+                let (batches, done) = (&batches, &done);
+                scope.spawn(move || {
+                    for batch in batches.iter().cycle() {
+                        if done.load(Ordering::Relaxed) {
+                            break;
+                        }
+
+                        let gen = reader.gen();
+                        let alive = batch.iter().filter(|id| reader.is_alive(**id)).count();
+                        if gen == reader.gen() {
+                            assert!(alive == 0 || alive == batch.len());
+                        }
+                    }
+                });
+            }
+
+            for batch in &batches {
+                let _ = shared.kill_multiple(&mut batch.clone());
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+    }
+}
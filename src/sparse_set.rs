@@ -0,0 +1,227 @@
+use crate::layout::{IdLayout, Split32x32};
+use crate::{Id, Killed, Valid, ValidId, Validator};
+use nonmax::NonMaxU32;
+
+/// Storage for values that only a few Ids have, packed densely for iteration.
+/// Every lookup checks the generation, so a stale Id never returns the value of a newer one.
+#[derive(Debug)]
+pub struct SparseSet<Arena, T, L: IdLayout = Split32x32> {
+    sparse: Vec<Option<NonMaxU32>>,
+    ids: Vec<Id<Arena, L>>,
+    values: Vec<T>,
+}
+
+impl<Arena, T, L: IdLayout> Default for SparseSet<Arena, T, L> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            sparse: Vec::new(),
+            ids: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<Arena, T: Clone, L: IdLayout> Clone for SparseSet<Arena, T, L> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            sparse: self.sparse.clone(),
+            ids: self.ids.clone(),
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<Arena, T, L: IdLayout> SparseSet<Arena, T, L> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    #[inline]
+    fn position(&self, id: Id<Arena, L>) -> Option<usize> {
+        let position = self.sparse.get(id.index()).copied().flatten()?.get() as usize;
+        (self.ids[position] == id).then_some(position)
+    }
+
+    /// Returns the previous value if one was inserted with the same Id.
+    /// A value left behind by an older generation of the same slot is replaced.
+    pub fn insert<V: ValidId<Arena = Arena, Layout = L>>(&mut self, id: V, value: T) -> Option<T> {
+        let id = id.id();
+        let index = id.index();
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+
+        if let Some(position) = self.sparse[index] {
+            let position = position.get() as usize;
+            let previous = std::mem::replace(&mut self.values[position], value);
+            let stale = std::mem::replace(&mut self.ids[position], id);
+            return (stale == id).then_some(previous);
+        }
+
+        self.sparse[index] = NonMaxU32::new(self.values.len() as u32);
+        self.ids.push(id);
+        self.values.push(value);
+        None
+    }
+
+    pub fn remove(&mut self, id: Id<Arena, L>) -> Option<T> {
+        let position = self.position(id)?;
+        self.sparse[id.index()] = None;
+
+        self.ids.swap_remove(position);
+        let value = self.values.swap_remove(position);
+
+        if let Some(moved) = self.ids.get(position) {
+            self.sparse[moved.index()] = NonMaxU32::new(position as u32);
+        }
+
+        Some(value)
+    }
+
+    /// Removes the values of every Id in the batch
+    pub fn remove_killed(&mut self, killed: &Killed<Arena, L>) {
+        for id in killed {
+            self.remove(*id.value);
+        }
+    }
+
+    #[inline]
+    pub fn contains(&self, id: Id<Arena, L>) -> bool {
+        self.position(id).is_some()
+    }
+
+    #[inline]
+    pub fn get(&self, id: Id<Arena, L>) -> Option<&T> {
+        let position = self.position(id)?;
+        self.values.get(position)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, id: Id<Arena, L>) -> Option<&mut T> {
+        let position = self.position(id)?;
+        self.values.get_mut(position)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (Id<Arena, L>, &T)> + '_ {
+        self.ids.iter().copied().zip(&self.values)
+    }
+
+    /// Iterates over the values whose Ids are alive according to the validator
+    #[inline]
+    pub fn iter_valid<'a, 'valid, V>(
+        &'a self,
+        validator: V,
+    ) -> impl Iterator<Item = (Valid<'valid, Id<Arena, L>>, &'a T)> + 'a
+    where
+        V: Validator<'valid, Arena, L> + 'a,
+    {
+        self.iter()
+            .filter_map(move |(id, value)| validator.validate(id).map(|id| (id, value)))
+    }
+
+    #[inline]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    #[inline]
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Allocator;
+
+    #[derive(Debug)]
+    struct Dynamic;
+    crate::dynamic_id!(Dynamic);
+
+    #[test]
+    fn stale_id_does_not_return_new_value() {
+        let mut allocator = Allocator::<Dynamic>::default();
+        let mut set = SparseSet::new();
+
+        let old = allocator.create().value;
+        set.insert(allocator.validate(old).unwrap(), 1);
+        allocator.kill(old);
+
+        let new = allocator.create().value;
+        assert_eq!(old.index(), new.index());
+        assert_eq!(None, set.get(new));
+
+        assert_eq!(None, set.insert(allocator.validate(new).unwrap(), 2));
+        assert_eq!(None, set.get(old));
+        assert_eq!(None, set.remove(old));
+        assert_eq!(Some(&2), set.get(new));
+        assert_eq!(1, set.len());
+    }
+
+    #[test]
+    fn remove_swaps_last_into_place() {
+        let mut allocator = Allocator::<Dynamic>::default();
+        let mut set = SparseSet::new();
+        let ids = (0..3).map(|_| allocator.create().value).collect::<Vec<_>>();
+        for (i, id) in ids.iter().enumerate() {
+            set.insert(allocator.validate(*id).unwrap(), i);
+        }
+
+        assert_eq!(Some(0), set.remove(ids[0]));
+
+        assert_eq!(&[2, 1], set.values());
+        assert_eq!(Some(&2), set.get(ids[2]));
+        assert_eq!(Some(&1), set.get(ids[1]));
+        assert!(!set.contains(ids[0]));
+    }
+
+    #[test]
+    fn remove_killed() {
+        let mut allocator = Allocator::<Dynamic>::default();
+        let mut set = SparseSet::new();
+        let ids = (0..3).map(|_| allocator.create().value).collect::<Vec<_>>();
+        for id in &ids {
+            set.insert(allocator.validate(*id).unwrap(), ());
+        }
+
+        let killed = allocator.kill_multiple(&mut vec![ids[0], ids[2]]);
+        set.remove_killed(&killed);
+
+        assert_eq!(
+            vec![ids[1]],
+            set.iter().map(|(id, _)| id).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn iter_valid_skips_dead() {
+        let mut allocator = Allocator::<Dynamic>::default();
+        let mut set = SparseSet::new();
+        let ids = (0..2).map(|_| allocator.create().value).collect::<Vec<_>>();
+        for id in &ids {
+            set.insert(allocator.validate(*id).unwrap(), id.index());
+        }
+        allocator.kill(ids[0]);
+
+        let valid = set.iter_valid(&allocator).collect::<Vec<_>>();
+
+        assert_eq!(1, valid.len());
+        assert_eq!(ids[1], valid[0].0.value);
+        assert_eq!(&1, valid[0].1);
+    }
+}
@@ -1,18 +1,20 @@
 use crate::alloc_gen::AllocGen;
+use crate::layout::{IdLayout, Split32x32};
 use crate::{Id, IdRange, Valid};
 
-pub trait Validator<'valid, Arena>: AsRef<AllocGen<Arena>> {
-    fn validate(&self, id: Id<Arena>) -> Option<Valid<'valid, Id<Arena>>>;
+pub trait Validator<'valid, Arena, L: IdLayout = Split32x32>: AsRef<AllocGen<Arena>> {
+    fn validate(&self, id: Id<Arena, L>) -> Option<Valid<'valid, Id<Arena, L>>>;
 }
 
 pub trait ValidId: Copy {
     /// Type is used instead of a generic parameter so that it can be referenced by `MaybeValidId`
     type Arena;
+    type Layout: IdLayout;
     fn index(self) -> usize;
-    fn id(self) -> Id<Self::Arena>;
+    fn id(self) -> Id<Self::Arena, Self::Layout>;
 
     #[inline]
-    fn valid<'valid>(self) -> Valid<'valid, Id<Self::Arena>>
+    fn valid<'valid>(self) -> Valid<'valid, Id<Self::Arena, Self::Layout>>
     where
         Self: 'valid,
     {
@@ -95,9 +97,9 @@ impl<V: ValidId> MaybeValidId for V {
     }
 }
 
-impl<'valid, Arena> MaybeValidId for Valid<'valid, Option<Id<Arena>>> {
+impl<'valid, Arena, L: IdLayout> MaybeValidId for Valid<'valid, Option<Id<Arena, L>>> {
     type Arena = Arena;
-    type Output = Valid<'valid, Id<Arena>>;
+    type Output = Valid<'valid, Id<Arena, L>>;
 
     #[inline]
     fn try_valid(&self) -> Option<Self::Output> {
@@ -105,9 +107,9 @@ impl<'valid, Arena> MaybeValidId for Valid<'valid, Option<Id<Arena>>> {
     }
 }
 
-impl<'a, 'valid, Arena> MaybeValidId for Valid<'valid, &'a Option<Id<Arena>>> {
+impl<'a, 'valid, Arena, L: IdLayout> MaybeValidId for Valid<'valid, &'a Option<Id<Arena, L>>> {
     type Arena = Arena;
-    type Output = Valid<'valid, &'a Id<Arena>>;
+    type Output = Valid<'valid, &'a Id<Arena, L>>;
 
     #[inline]
     fn try_valid(&self) -> Option<Self::Output> {
@@ -115,9 +117,9 @@ impl<'a, 'valid, Arena> MaybeValidId for Valid<'valid, &'a Option<Id<Arena>>> {
     }
 }
 
-impl<'valid, Arena> MaybeValidId for &'_ Valid<'valid, Option<Id<Arena>>> {
+impl<'valid, Arena, L: IdLayout> MaybeValidId for &'_ Valid<'valid, Option<Id<Arena, L>>> {
     type Arena = Arena;
-    type Output = Valid<'valid, Id<Arena>>;
+    type Output = Valid<'valid, Id<Arena, L>>;
 
     #[inline]
     fn try_valid(&self) -> Option<Self::Output> {
@@ -125,9 +127,9 @@ impl<'valid, Arena> MaybeValidId for &'_ Valid<'valid, Option<Id<Arena>>> {
     }
 }
 
-impl<'a, 'valid, Arena> MaybeValidId for &'_ Valid<'valid, &'a Option<Id<Arena>>> {
+impl<'a, 'valid, Arena, L: IdLayout> MaybeValidId for &'_ Valid<'valid, &'a Option<Id<Arena, L>>> {
     type Arena = Arena;
-    type Output = Valid<'valid, &'a Id<Arena>>;
+    type Output = Valid<'valid, &'a Id<Arena, L>>;
 
     #[inline]
     fn try_valid(&self) -> Option<Self::Output> {
@@ -137,5 +139,6 @@ impl<'a, 'valid, Arena> MaybeValidId for &'_ Valid<'valid, &'a Option<Id<Arena>>
 
 pub trait ValidRange: Copy {
     type Arena;
-    fn range(self) -> IdRange<Self::Arena>;
+    type Layout: IdLayout;
+    fn range(self) -> IdRange<Self::Arena, Self::Layout>;
 }
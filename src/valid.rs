@@ -1,3 +1,4 @@
+use crate::layout::IdLayout;
 use crate::{Id, IdRange, ValidId, ValidRange};
 use iter_context::ContextualIterator;
 use ref_cast::RefCast;
@@ -87,8 +88,9 @@ impl<'valid, T: Copy> Valid<'valid, &T> {
     }
 }
 
-impl<'valid, Arena> ValidId for Valid<'valid, Id<Arena>> {
+impl<'valid, Arena, L: IdLayout> ValidId for Valid<'valid, Id<Arena, L>> {
     type Arena = Arena;
+    type Layout = L;
 
     #[inline]
     fn index(self) -> usize {
@@ -96,13 +98,14 @@ impl<'valid, Arena> ValidId for Valid<'valid, Id<Arena>> {
     }
 
     #[inline]
-    fn id(self) -> Id<Arena> {
+    fn id(self) -> Id<Arena, L> {
         self.value
     }
 }
 
-impl<'valid, Arena> ValidId for Valid<'valid, &'_ Id<Arena>> {
+impl<'valid, Arena, L: IdLayout> ValidId for Valid<'valid, &'_ Id<Arena, L>> {
     type Arena = Arena;
+    type Layout = L;
 
     #[inline]
     fn index(self) -> usize {
@@ -110,13 +113,14 @@ impl<'valid, Arena> ValidId for Valid<'valid, &'_ Id<Arena>> {
     }
 
     #[inline]
-    fn id(self) -> Id<Arena> {
+    fn id(self) -> Id<Arena, L> {
         *self.value
     }
 }
 
-impl<'valid, Arena> ValidId for &'_ Valid<'valid, Id<Arena>> {
+impl<'valid, Arena, L: IdLayout> ValidId for &'_ Valid<'valid, Id<Arena, L>> {
     type Arena = Arena;
+    type Layout = L;
 
     #[inline]
     fn index(self) -> usize {
@@ -124,13 +128,14 @@ impl<'valid, Arena> ValidId for &'_ Valid<'valid, Id<Arena>> {
     }
 
     #[inline]
-    fn id(self) -> Id<Arena> {
+    fn id(self) -> Id<Arena, L> {
         self.value
     }
 }
 
-impl<'valid, Arena> ValidId for &'_ Valid<'valid, &Id<Arena>> {
+impl<'valid, Arena, L: IdLayout> ValidId for &'_ Valid<'valid, &Id<Arena, L>> {
     type Arena = Arena;
+    type Layout = L;
 
     #[inline]
     fn index(self) -> usize {
@@ -138,7 +143,7 @@ impl<'valid, Arena> ValidId for &'_ Valid<'valid, &Id<Arena>> {
     }
 
     #[inline]
-    fn id(self) -> Id<Arena> {
+    fn id(self) -> Id<Arena, L> {
         *self.value
     }
 }
@@ -222,34 +227,38 @@ impl<'valid, Iter: Iterator> Iterator for ValidIter<'valid, Iter> {
     }
 }
 
-impl<'valid, Arena> ValidRange for Valid<'valid, IdRange<Arena>> {
+impl<'valid, Arena, L: IdLayout> ValidRange for Valid<'valid, IdRange<Arena, L>> {
     type Arena = Arena;
+    type Layout = L;
     #[inline]
-    fn range(self) -> IdRange<Arena> {
+    fn range(self) -> IdRange<Arena, L> {
         self.value
     }
 }
 
-impl<'valid, Arena> ValidRange for Valid<'valid, &IdRange<Arena>> {
+impl<'valid, Arena, L: IdLayout> ValidRange for Valid<'valid, &IdRange<Arena, L>> {
     type Arena = Arena;
+    type Layout = L;
     #[inline]
-    fn range(self) -> IdRange<Arena> {
+    fn range(self) -> IdRange<Arena, L> {
         *self.value
     }
 }
 
-impl<'valid, Arena> ValidRange for &Valid<'valid, IdRange<Arena>> {
+impl<'valid, Arena, L: IdLayout> ValidRange for &Valid<'valid, IdRange<Arena, L>> {
     type Arena = Arena;
+    type Layout = L;
     #[inline]
-    fn range(self) -> IdRange<Arena> {
+    fn range(self) -> IdRange<Arena, L> {
         self.value
     }
 }
 
-impl<'valid, Arena> ValidRange for &Valid<'valid, &IdRange<Arena>> {
+impl<'valid, Arena, L: IdLayout> ValidRange for &Valid<'valid, &IdRange<Arena, L>> {
     type Arena = Arena;
+    type Layout = L;
     #[inline]
-    fn range(self) -> IdRange<Arena> {
+    fn range(self) -> IdRange<Arena, L> {
         *self.value
     }
 }
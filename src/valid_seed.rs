@@ -0,0 +1,169 @@
+use crate::layout::IdLayout;
+use crate::{Id, Valid, Validator};
+use serde::de::{DeserializeSeed, Error};
+use serde::{Deserialize, Deserializer};
+use std::marker::PhantomData;
+
+/// Determines what happens to dead Ids found while deserializing with a `ValidSeed`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DeadIdPolicy {
+    /// Any dead Id is a deserialization error
+    Error,
+    /// Dead Ids are removed from a `Vec` and turned into `None` for an `Option`.
+    /// A single `Id` has nothing to fall back to, so it is still an error.
+    Discard,
+}
+
+/// Deserializes `Id<Arena>`, `Option<Id<Arena>>` or `Vec<Id<Arena>>` directly into a `Valid` value
+/// by checking each Id against a `Validator`
+pub struct ValidSeed<'valid, V, T> {
+    validator: V,
+    policy: DeadIdPolicy,
+    valid: PhantomData<&'valid ()>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'valid, V, T> ValidSeed<'valid, V, T> {
+    #[inline]
+    pub fn new(validator: V, policy: DeadIdPolicy) -> Self {
+        Self {
+            validator,
+            policy,
+            valid: PhantomData,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, 'valid, Arena, L: IdLayout, V> DeserializeSeed<'de> for ValidSeed<'valid, V, Id<Arena, L>>
+where
+    V: Validator<'valid, Arena, L>,
+{
+    type Value = Valid<'valid, Id<Arena, L>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let id = Id::<Arena, L>::deserialize(deserializer)?;
+        self.validator.validate(id).ok_or_else(|| dead_id(id))
+    }
+}
+
+impl<'de, 'valid, Arena, L: IdLayout, V> DeserializeSeed<'de>
+    for ValidSeed<'valid, V, Option<Id<Arena, L>>>
+where
+    V: Validator<'valid, Arena, L>,
+{
+    type Value = Valid<'valid, Option<Id<Arena, L>>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let id = match Option::<Id<Arena, L>>::deserialize(deserializer)? {
+            Some(id) => id,
+            None => return Ok(Valid::new(None)),
+        };
+
+        match (self.validator.validate(id), self.policy) {
+            (Some(valid), _) => Ok(valid.map(Some)),
+            (None, DeadIdPolicy::Discard) => Ok(Valid::new(None)),
+            (None, DeadIdPolicy::Error) => Err(dead_id(id)),
+        }
+    }
+}
+
+impl<'de, 'valid, Arena, L: IdLayout, V> DeserializeSeed<'de>
+    for ValidSeed<'valid, V, Vec<Id<Arena, L>>>
+where
+    V: Validator<'valid, Arena, L>,
+{
+    type Value = Valid<'valid, Vec<Id<Arena, L>>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let mut ids = Vec::<Id<Arena, L>>::deserialize(deserializer)?;
+
+        match self.policy {
+            DeadIdPolicy::Discard => ids.retain(|id| self.validator.validate(*id).is_some()),
+            DeadIdPolicy::Error => {
+                if let Some(id) = ids
+                    .iter()
+                    .find(|id| self.validator.validate(**id).is_none())
+                {
+                    return Err(dead_id(*id));
+                }
+            }
+        }
+
+        Ok(Valid::new(ids))
+    }
+}
+
+fn dead_id<Arena, L: IdLayout, E: Error>(id: Id<Arena, L>) -> E {
+    E::custom(format_args!("dead id: {:?}", id.untyped()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Allocator;
+
+    fn allocator() -> (Allocator<()>, Id<()>, Id<()>) {
+        let mut allocator = Allocator::<()>::default();
+        let alive = allocator.create().value;
+        let dead = allocator.create().value;
+        allocator.kill(dead);
+        (allocator, alive, dead)
+    }
+
+    #[test]
+    fn id_given_alive() {
+        let (allocator, alive, _) = allocator();
+        let json = serde_json::to_string(&alive).unwrap();
+
+        let seed = ValidSeed::<_, Id<()>>::new(&allocator, DeadIdPolicy::Error);
+        let valid = seed
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .unwrap();
+
+        assert_eq!(alive, valid.value);
+    }
+
+    #[test]
+    fn id_given_dead_discard_returns_error() {
+        let (allocator, _, dead) = allocator();
+        let json = serde_json::to_string(&dead).unwrap();
+
+        let seed = ValidSeed::<_, Id<()>>::new(&allocator, DeadIdPolicy::Discard);
+        let result = seed.deserialize(&mut serde_json::Deserializer::from_str(&json));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn option_given_dead() {
+        let (allocator, _, dead) = allocator();
+        let json = serde_json::to_string(&Some(dead)).unwrap();
+
+        let seed = ValidSeed::<_, Option<Id<()>>>::new(&allocator, DeadIdPolicy::Discard);
+        let valid = seed
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .unwrap();
+        assert_eq!(None, valid.value);
+
+        let seed = ValidSeed::<_, Option<Id<()>>>::new(&allocator, DeadIdPolicy::Error);
+        let result = seed.deserialize(&mut serde_json::Deserializer::from_str(&json));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn vec_given_dead() {
+        let (allocator, alive, dead) = allocator();
+        let json = serde_json::to_string(&vec![alive, dead, alive]).unwrap();
+
+        let seed = ValidSeed::<_, Vec<Id<()>>>::new(&allocator, DeadIdPolicy::Discard);
+        let valid = seed
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .unwrap();
+        assert_eq!(vec![alive, alive], valid.value);
+
+        let seed = ValidSeed::<_, Vec<Id<()>>>::new(&allocator, DeadIdPolicy::Error);
+        let result = seed.deserialize(&mut serde_json::Deserializer::from_str(&json));
+        assert!(result.is_err());
+    }
+}